@@ -1,20 +1,82 @@
-use crossterm::event::{read, Event, KeyEvent, KeyEventKind};
+use crossterm::event::{read, Event, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind};
 use std::{
-    env, 
+    env,
     io::Error,
     panic::{set_hook, take_hook},
+    path::PathBuf,
 };
 
 mod terminal;
 mod view;
 mod editorcommand;
-use terminal::Terminal;
-use view::View;
+mod messagebar;
+mod layout;
+mod tabbar;
+mod commandline;
+mod prompt;
+mod pathprompt;
+mod filetype;
+mod keymap;
+mod config;
+mod editorconfig;
+mod annotation;
+mod vimode;
+mod killring;
+mod width;
+use terminal::{Position, Size, Terminal};
+use view::{Location, View};
 use editorcommand::EditorCommand;
+use messagebar::MessageBar;
+use layout::{Area, Layout};
+use tabbar::TabBar;
+use commandline::{CommandLine, ExCommand};
+use prompt::{Prompt, PromptEvent};
+use pathprompt::{file_identity, resolve_path, PathPrompt};
+use editorcommand::SplitDirection;
+use keymap::{Keymap, KeymapResult, Profile};
+use vimode::{ViMode, ViResult};
+use killring::KillRing;
+use config::{Config, Settings};
+use width::WidthModel;
+
+/// 有未保存修改时，需要连续按下退出/关闭命令的次数
+const QUIT_TIMES: u8 = 3;
+
+/// 当前激活的输入框，激活时占据消息栏所在行
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PromptKind {
+    Command, // 命令行
+    Search, // 增量查找
+    GotoLine, // 跳转到指定行
+    SaveAs, // 输入新文件名保存
+    Open, // 输入文件路径打开
+}
 
 pub struct Editor {
     should_quit: bool,
-    view: View,
+    views: Vec<View>, // 所有打开的buffer，记录每个buffer最近一次的光标位置和滚动偏移
+    layout: Layout, // 窗格布局，每个窗格显示views中某个buffer的一个view
+    message_bar: MessageBar, // 底部消息栏
+    tab_bar: TabBar, // 顶部标签栏
+    show_tab_bar: bool, // 是否显示标签栏
+    command_line: CommandLine, // 底部命令行
+    search_prompt: Prompt, // 查找输入框
+    goto_prompt: Prompt, // 跳转输入框
+    active_prompt: Option<PromptKind>, // 当前激活的输入框
+    keymap: Keymap, // 按键绑定
+    vi: Option<ViMode>, // vi模式编辑层，按键绑定方案为vi时启用
+    kill_ring: KillRing, // 最近复制或剪切的文本
+    kill_continues: bool, // 上一个命令是否为剪切，连续剪切的文本追加到同一条目
+    last_yank: Option<(Location, Location)>, // 上一个命令插入的kill ring文本的范围，用于替换为更早的条目
+    config: Config, // 编辑器配置
+    search_origin: Location, // 开始查找时的光标位置，取消查找时恢复
+    last_search: String, // 最近一次查找的内容，用于查找下一个或上一个匹配
+    save_as_prompt: PathPrompt, // 另存为输入框
+    open_prompt: PathPrompt, // 打开文件输入框
+    working_dir: PathBuf, // 工作目录，相对路径基于该目录解析
+    terminal_size: Size, // terminal尺寸
+    quit_times: u8, // 有未保存修改时，剩余需要按下退出命令的次数
+    close_times: u8, // 当前buffer有未保存修改时，剩余需要按下关闭命令的次数
 }
 
 impl Editor {
@@ -29,18 +91,94 @@ impl Editor {
 
         // 初始化Editor中数据
         Terminal::initialize()?;
-        let mut view = View::default();
-        let args: Vec<String> = env::args().collect();
-        // 读取命令行参数，将对应文件中数据加载到Editor中
-        if let Some(first_arg) = args.get(1) {
-            view.load(&first_arg);
+        let mut message_bar = MessageBar::default();
+        let (keymap, mut config_errors) = Keymap::load();
+        let (config, errors) = Config::load();
+        config_errors.extend(errors);
+        // 在创建任何行之前确定字素宽度，开启探测时测量终端实际显示emoji序列的宽度
+        if config.probe_widths() {
+            WidthModel::probe(|sample| Terminal::measure_width(sample).ok()).install();
+        }
+        let working_dir = env::current_dir().unwrap_or_default();
+        // 读取命令行参数，将每个参数对应文件中数据加载到各自的buffer中，同一文件只打开一次
+        let mut views = Vec::new();
+        let mut opened = Vec::new();
+        let args: Vec<String> = env::args().skip(1).collect();
+        for file_name in &args {
+            let identity = file_identity(file_name, &working_dir);
+            if opened.contains(&identity) {
+                continue;
+            }
+            let (settings, errors) = config.settings_for(Some(file_name));
+            config_errors.extend(errors);
+            let mut view = View::default();
+            if let Err(err) = view.load(file_name, settings) {
+                message_bar.update_message(&format!("ERR: Could not open {file_name}: {err}"));
+                continue;
+            }
+            opened.push(identity);
+            views.push(view);
+        }
+        // 没有成功打开任何文件时，创建一个空buffer
+        if views.is_empty() {
+            let mut view = View::default();
+            view.set_settings(config.settings_for(None).0);
+            views.push(view);
+        }
+        if views.len() > 1 {
+            message_bar.update_message(&format!("Opened {} buffers", views.len()));
         }
+        Self::report_config_errors(&mut message_bar, &config_errors);
+        let layout = Layout::new(views.first().cloned().unwrap_or_default(), 0);
         Ok(Self {
             should_quit: false,
-            view,
+            views,
+            layout,
+            message_bar,
+            tab_bar: TabBar::default(),
+            show_tab_bar: true,
+            command_line: CommandLine::default(),
+            search_prompt: Prompt::new("Search: "),
+            goto_prompt: Prompt::new("Go to line: "),
+            active_prompt: None,
+            vi: (keymap.profile() == Profile::Vi).then(ViMode::default),
+            keymap,
+            kill_ring: KillRing::default(),
+            kill_continues: false,
+            last_yank: None,
+            config,
+            search_origin: Location::default(),
+            last_search: String::new(),
+            save_as_prompt: PathPrompt::new("Save as: "),
+            open_prompt: PathPrompt::new("Open: "),
+            working_dir,
+            terminal_size: Terminal::size().unwrap_or_default(),
+            quit_times: QUIT_TIMES,
+            close_times: QUIT_TIMES,
         })
     }
 
+    /// 配置文件有错误时显示第一条错误，其余错误的条目已被跳过
+    fn report_config_errors(message_bar: &mut MessageBar, errors: &[String]) {
+        if let Some(err) = errors.first() {
+            let more = errors.len().saturating_sub(1);
+            let suffix = if more > 0 { format!(" (+{more} more)") } else { String::new() };
+            message_bar.update_message(&format!("ERR: {err}{suffix}"));
+        }
+    }
+
+    /// 窗格可用的区域，显示标签栏时第一行留给标签栏，最后一行留给消息栏
+    const fn pane_area(&self) -> Area {
+        let top = if self.show_tab_bar { 1 } else { 0 };
+        Area {
+            origin: Position { col: 0, row: top },
+            size: Size {
+                height: self.terminal_size.height.saturating_sub(1).saturating_sub(top),
+                width: self.terminal_size.width,
+            },
+        }
+    }
+
     /// Editor核心运行函数
     /// 循环读取事件，渲染view
     pub fn run(&mut self) {
@@ -66,12 +204,69 @@ impl Editor {
     /// 先隐藏光标，然后渲染view，最后显示光标，整个事件放在queue中，最后一次执行所有操作
     fn refresh_screen(&mut self) {
         let _ = Terminal::hide_caret();
-        self.view.render();
-        let _ = Terminal::move_caret_to(self.view.crate_position());
+        let Size { height, width } = self.terminal_size;
+        let bottom = height.saturating_sub(1);
+        match self.active_prompt {
+            Some(PromptKind::Command) => self.command_line.render(bottom, width),
+            Some(PromptKind::Search) => self.search_prompt.render(bottom, width, ""),
+            Some(PromptKind::GotoLine) => self.goto_prompt.render(bottom, width, ""),
+            Some(PromptKind::SaveAs) => self.save_as_prompt.render(bottom, width),
+            Some(PromptKind::Open) => self.open_prompt.render(bottom, width),
+            None => self.message_bar.render(bottom, width),
+        }
+        if self.show_tab_bar {
+            let current = self.current_buffer();
+            self.tab_bar.render(0, width, &self.views, current);
+        }
+        self.render_panes();
+        let caret = match self.active_prompt {
+            Some(PromptKind::Command) => Some(self.command_line.caret_position(bottom)),
+            Some(PromptKind::Search) => Some(self.search_prompt.caret_position(bottom)),
+            Some(PromptKind::GotoLine) => Some(self.goto_prompt.caret_position(bottom)),
+            Some(PromptKind::SaveAs) => Some(self.save_as_prompt.caret_position(bottom)),
+            Some(PromptKind::Open) => Some(self.open_prompt.caret_position(bottom)),
+            None => self.layout.focused_pane().map(|pane| pane.view.crate_position()),
+        };
+        if let Some(caret) = caret {
+            let _ = Terminal::move_caret_to(caret);
+        }
         let _ = Terminal::show_caret();
         let _ = Terminal::execute();
     }
 
+    /// 渲染所有窗格，每个窗格的最后一行为状态行，左右排列的窗格之间绘制分割线
+    fn render_panes(&mut self) {
+        let area = self.pane_area();
+        for (id, pane_area) in self.layout.areas(area) {
+            if let Some(pane) = self.layout.panes_mut().iter_mut().find(|pane| pane.id == id) {
+                let view_size = Size {
+                    height: pane_area.size.height.saturating_sub(1),
+                    width: pane_area.size.width,
+                };
+                pane.view.set_area(pane_area.origin, view_size);
+                pane.view.render();
+            }
+            if let Some(pane) = self.layout.panes().iter().find(|pane| pane.id == id) {
+                if pane_area.size.height > 0 {
+                    let status_at = Position {
+                        col: pane_area.origin.col,
+                        row: pane_area.origin.row.saturating_add(pane_area.size.height).saturating_sub(1),
+                    };
+                    let focused = self.layout.is_focused(pane);
+                    let mode = self.vi.as_ref().filter(|_| focused).map(|vi| vi.mode().to_string());
+                    pane.render_status(status_at, pane_area.size.width, focused, mode.as_deref());
+                }
+            }
+        }
+        for (at, separator_height) in self.layout.separators(area) {
+            for row in 0..separator_height {
+                let position = Position { col: at.col, row: at.row.saturating_add(row) };
+                let _ = Terminal::move_caret_to(position);
+                let _ = Terminal::print('│');
+            }
+        }
+    }
+
     /// 对event进行处理
     #[allow(clippy::needless_pass_by_value)]
     fn evaluate_event(&mut self, event: Event) {
@@ -79,20 +274,671 @@ impl Editor {
         let should_process = match &event {
             Event::Key(KeyEvent { kind, .. }) => kind == &KeyEventKind::Press,
             Event::Resize(_, _) => true,
+            Event::Mouse(MouseEvent { kind, .. }) => kind == &MouseEventKind::Down(MouseButton::Left),
             _ => false,
         };
         if should_process {
-            // 将crossterm中的event转换为自定义的EditorCommand
-            if let Ok(command) = EditorCommand::try_from(event) {
-                if matches!(command, EditorCommand::Quit) {
-                    // 退出Editor
-                    self.should_quit = true;
+            // 输入框激活时按键由输入框处理
+            if let Some(kind) = self.active_prompt {
+                if let Event::Key(key) = event {
+                    self.handle_prompt_key(kind, key);
+                    return;
+                }
+            }
+            // 启用vi模式时按键先由vi模式处理
+            if let (Event::Key(key), Some(vi)) = (event.clone(), self.vi.as_mut()) {
+                let to_line_end = self.layout.focused_pane().map_or(0, |pane| pane.view.graphemes_to_line_end());
+                match vi.handle_key(key, to_line_end) {
+                    ViResult::Commands(commands) => {
+                        for command in commands {
+                            self.process_command(command);
+                        }
+                        return;
+                    }
+                    ViResult::Pending => return,
+                    ViResult::Unhandled => (),
+                }
+            }
+            // 按键通过按键绑定表转换为命令，其余事件直接转换为自定义的EditorCommand
+            if let Event::Key(key) = event {
+                match self.keymap.lookup(key) {
+                    KeymapResult::Command(command) => self.process_command(command),
+                    KeymapResult::Pending(keys) => self.message_bar.update_message(&format!("{keys} -")),
+                    KeymapResult::UnboundSequence(keys) => {
+                        self.message_bar.update_message(&format!("{keys} is not bound"));
+                    }
+                    KeymapResult::Unbound => (),
+                }
+            } else if let Ok(command) = EditorCommand::try_from(event) {
+                self.process_command(command);
+            }
+        }
+    }
+
+    /// 执行命令，与buffer列表和窗格相关的命令在Editor中处理，其余交给当前窗格的view
+    fn process_command(&mut self, command: EditorCommand) {
+        // 连续按下才生效的确认命令，被其他命令打断时重新计数
+        if !matches!(command, EditorCommand::Quit) {
+            self.quit_times = QUIT_TIMES;
+        }
+        if !matches!(command, EditorCommand::CloseBuffer) {
+            self.close_times = QUIT_TIMES;
+        }
+        // 剪切的追加和插入文本的替换只对紧接着的命令有效，循环选择结束后粘贴最新的条目
+        let kill_continues = std::mem::take(&mut self.kill_continues);
+        let last_yank = self.last_yank.take();
+        if !matches!(command, EditorCommand::YankPop) {
+            self.kill_ring.reset_rotation();
+        }
+        match command {
+            EditorCommand::Quit => self.handle_quit(),
+            EditorCommand::Resize(size) => self.resize(size),
+            EditorCommand::Save => self.handle_save(),
+            EditorCommand::NextBuffer => self.switch_buffer(true),
+            EditorCommand::PrevBuffer => self.switch_buffer(false),
+            EditorCommand::ListBuffers => self.list_buffers(),
+            EditorCommand::CloseBuffer => self.handle_close_buffer(),
+            EditorCommand::Split(direction) => {
+                self.layout.split(direction);
+                self.mark_all_panes_redraw();
+            }
+            EditorCommand::ClosePane => {
+                if self.layout.close_focused() {
+                    self.on_focus_changed();
+                } else {
+                    self.message_bar.update_message("Cannot close the last pane");
+                }
+            }
+            EditorCommand::FocusPane(direction) => {
+                let area = self.pane_area();
+                self.layout.focus_direction(direction, area);
+                self.on_focus_changed();
+            }
+            EditorCommand::FocusNextPane => {
+                self.layout.focus_next();
+                self.on_focus_changed();
+            }
+            EditorCommand::ResizePane(delta) => {
+                self.layout.resize_focused(delta);
+                self.mark_all_panes_redraw();
+            }
+            EditorCommand::ToggleTabBar => {
+                self.show_tab_bar = !self.show_tab_bar;
+                self.mark_all_panes_redraw();
+            }
+            EditorCommand::Click(position) => self.handle_click(position),
+            EditorCommand::AddCaretAt(_) | EditorCommand::AddNextOccurrence => self.add_caret(command),
+            EditorCommand::OpenCommandLine => {
+                self.command_line.open();
+                self.active_prompt = Some(PromptKind::Command);
+            }
+            EditorCommand::Search => {
+                self.search_origin = self.focused_location();
+                self.search_prompt.reset();
+                self.active_prompt = Some(PromptKind::Search);
+            }
+            EditorCommand::SearchNext | EditorCommand::SearchPrev => {
+                self.search_next(matches!(command, EditorCommand::SearchNext));
+            }
+            EditorCommand::GotoLine => {
+                self.goto_prompt.reset();
+                self.active_prompt = Some(PromptKind::GotoLine);
+            }
+            EditorCommand::SaveAs => self.open_save_as_prompt(),
+            EditorCommand::Open => {
+                self.open_prompt.open("");
+                self.active_prompt = Some(PromptKind::Open);
+            }
+            EditorCommand::Copy | EditorCommand::Cut | EditorCommand::Change | EditorCommand::KillLine => {
+                self.kill(command, kill_continues);
+            }
+            EditorCommand::Paste | EditorCommand::PasteAfter => {
+                let (Some(pane), Some(kill)) = (self.layout.focused_pane_mut(), self.kill_ring.current()) else {
+                    return;
+                };
+                let after = matches!(command, EditorCommand::PasteAfter);
+                pane.view.paste(&kill.text, kill.linewise, after);
+                self.mark_buffer_redraw();
+            }
+            EditorCommand::ToggleComment => self.toggle_comment(),
+            EditorCommand::Yank => self.yank(None),
+            EditorCommand::YankPop => {
+                if last_yank.is_some() {
+                    self.kill_ring.rotate();
+                    self.yank(last_yank);
                 } else {
-                    // 其他事件处理
-                    self.view.handle_command(command);
+                    self.message_bar.update_message("Previous command was not a yank");
+                }
+            }
+            _ => {
+                let Some(pane) = self.layout.focused_pane_mut() else {
+                    return;
+                };
+                pane.view.handle_command(command);
+                self.mark_buffer_redraw();
+            }
+        }
+    }
+
+    /// 复制或剪切文本到kill ring，`append`为true时剪切的文本追加到最新的条目
+    fn kill(&mut self, command: EditorCommand, append: bool) {
+        let Some(pane) = self.layout.focused_pane_mut() else {
+            return;
+        };
+        let (text, linewise) = match command {
+            EditorCommand::KillLine => (pane.view.kill_line(), false),
+            _ => match pane.view.selected_text() {
+                Some(selected) => selected,
+                None => return,
+            },
+        };
+        match command {
+            EditorCommand::Copy => pane.view.collapse_selection(),
+            EditorCommand::Change => pane.view.delete_selection(true),
+            EditorCommand::Cut => pane.view.delete_selection(false),
+            _ => (),
+        }
+        if matches!(command, EditorCommand::Cut | EditorCommand::KillLine) {
+            if append {
+                self.kill_ring.append(text, linewise);
+            } else {
+                self.kill_ring.push(text, linewise);
+            }
+            self.kill_continues = true;
+        } else {
+            self.kill_ring.push(text, linewise);
+        }
+        self.mark_buffer_redraw();
+    }
+
+    /// 添加光标，按住Alt点击其他窗格时先切换焦点；没有更多出现位置时提示
+    fn add_caret(&mut self, command: EditorCommand) {
+        if let EditorCommand::AddCaretAt(position) = command {
+            if self.layout.focus_at(position, self.pane_area()) {
+                self.on_focus_changed();
+            }
+        }
+        let Some(pane) = self.layout.focused_pane_mut() else {
+            return;
+        };
+        if matches!(command, EditorCommand::AddNextOccurrence) {
+            if !pane.view.add_next_occurrence() {
+                self.message_bar.update_message("No more occurrences");
+            }
+        } else {
+            pane.view.handle_command(command);
+        }
+    }
+
+    /// 切换当前行或选中各行的行注释，文件类型没有行注释时提示
+    fn toggle_comment(&mut self) {
+        let Some(pane) = self.layout.focused_pane_mut() else {
+            return;
+        };
+        if pane.view.toggle_comment() {
+            self.mark_buffer_redraw();
+        } else {
+            let file_type = pane.view.file_type();
+            self.message_bar.update_message(&format!("No line comment for {file_type}"));
+        }
+    }
+
+    /// 在光标处插入kill ring中的当前条目，光标移动到插入的文本之后
+    /// `replace`为之前插入的文本的范围，替换为当前条目，替换作为一步撤销
+    fn yank(&mut self, replace: Option<(Location, Location)>) {
+        let (Some(pane), Some(kill)) = (self.layout.focused_pane_mut(), self.kill_ring.current()) else {
+            return;
+        };
+        // 粘贴和替换粘贴只作用于主光标
+        pane.view.clear_carets();
+        let start = if let Some((start, end)) = replace {
+            pane.view.replace_text(start, end, &kill.text);
+            start
+        } else {
+            let start = pane.view.text_location();
+            pane.view.paste(&kill.text, false, false);
+            start
+        };
+        self.last_yank = Some((start, pane.view.text_location()));
+        self.mark_buffer_redraw();
+    }
+
+    /// 同一buffer可能显示在多个窗格中，修改需要在所有窗格中立即可见
+    /// 其他窗格的光标和选择可能因修改而超出文本范围，将其限制在合法的位置
+    fn mark_buffer_redraw(&mut self) {
+        let Some((focused_id, buffer_index)) = self.layout.focused_pane().map(|pane| (pane.id, pane.buffer_index)) else {
+            return;
+        };
+        for pane in self.layout.panes_mut() {
+            if pane.buffer_index == buffer_index {
+                if pane.id != focused_id {
+                    pane.view.snap_to_valid_location();
+                }
+                pane.view.mark_redraw();
+            }
+        }
+    }
+
+    /// 当前窗格的光标位置
+    fn focused_location(&self) -> Location {
+        self.layout
+            .focused_pane()
+            .map_or_else(Location::default, |pane| pane.view.text_location())
+    }
+
+    /// 处理输入框中的按键，提交或取消时关闭输入框
+    /// 查找时按下查找或查找下一个/上一个的按键，从当前匹配处查找下一个或上一个匹配
+    fn handle_prompt_key(&mut self, kind: PromptKind, key: KeyEvent) {
+        if kind == PromptKind::Search {
+            match self.keymap.binding(key) {
+                Some(EditorCommand::Search | EditorCommand::SearchNext) => return self.search_next(true),
+                Some(EditorCommand::SearchPrev) => return self.search_next(false),
+                _ => (),
+            }
+        }
+        let event = match kind {
+            PromptKind::Command => self.command_line.handle_key(key),
+            PromptKind::Search => self.search_prompt.handle_key(key),
+            PromptKind::GotoLine => self.goto_prompt.handle_key(key),
+            PromptKind::SaveAs => self.save_as_prompt.handle_key(key, &self.working_dir),
+            PromptKind::Open => self.open_prompt.handle_key(key, &self.working_dir),
+        };
+        if matches!(event, PromptEvent::Submitted(_) | PromptEvent::Cancelled) {
+            self.active_prompt = None;
+            self.message_bar.mark_redraw();
+        }
+        match (kind, event) {
+            (PromptKind::Command, PromptEvent::Submitted(input)) => {
+                if input.trim().is_empty() {
+                    return;
+                }
+                match ExCommand::try_from(input.as_str()) {
+                    Ok(command) => self.execute_ex_command(command),
+                    Err(err) => self.message_bar.update_message(&err),
+                }
+            }
+            (PromptKind::Search, PromptEvent::Changed(query)) => self.search(&query),
+            (PromptKind::Search, PromptEvent::Cancelled) => {
+                // 取消查找时回到查找前的位置
+                let origin = self.search_origin;
+                if let Some(pane) = self.layout.focused_pane_mut() {
+                    pane.view.set_text_location(origin);
+                }
+            }
+            (PromptKind::GotoLine, PromptEvent::Submitted(input)) => match input.trim().parse() {
+                Ok(line_number) => self.execute_ex_command(ExCommand::Goto(line_number)),
+                Err(_) => self.message_bar.update_message(&format!("Invalid line number: {input}")),
+            },
+            (PromptKind::SaveAs, PromptEvent::Submitted(path)) => self.save_as(path.trim()),
+            (PromptKind::Open, PromptEvent::Submitted(path)) => self.open_file(path.trim()),
+            _ => (),
+        }
+    }
+
+    /// 增量查找，每次输入变化时从查找开始的位置重新查找
+    fn search(&mut self, query: &str) {
+        self.last_search = query.to_string();
+        let origin = self.search_origin;
+        let Some(pane) = self.layout.focused_pane_mut() else {
+            return;
+        };
+        if query.is_empty() {
+            pane.view.set_text_location(origin);
+        } else if !pane.view.search_from(query, origin) {
+            self.message_bar.update_message(&format!("Not found: {query}"));
+        }
+    }
+
+    /// 从光标处查找上次查找内容的下一个或上一个匹配
+    fn search_next(&mut self, forward: bool) {
+        if self.last_search.is_empty() {
+            self.message_bar.update_message("No previous search");
+            return;
+        }
+        let Some(pane) = self.layout.focused_pane_mut() else {
+            return;
+        };
+        if !pane.view.search_next(&self.last_search, forward) {
+            self.message_bar.update_message(&format!("Not found: {}", self.last_search));
+        }
+    }
+
+    /// 执行命令行中输入的命令
+    fn execute_ex_command(&mut self, command: ExCommand) {
+        match command {
+            ExCommand::Save => self.process_command(EditorCommand::Save),
+            ExCommand::SaveAs(path) => self.save_as(&path),
+            ExCommand::Open(path) => self.open_file(&path),
+            ExCommand::Goto(line_number) => {
+                if let Some(pane) = self.layout.focused_pane_mut() {
+                    pane.view.goto_line(line_number);
+                }
+            }
+            ExCommand::Set(option, value) => self.set_option(&option, &value),
+            ExCommand::Replace(from, to) => {
+                let Some(pane) = self.layout.focused_pane_mut() else {
+                    return;
+                };
+                let count = pane.view.replace_all(&from, &to);
+                self.message_bar.update_message(&format!("Replaced {count} occurrence(s)"));
+                self.mark_buffer_redraw();
+                self.mark_all_panes_redraw();
+            }
+            ExCommand::Next => self.process_command(EditorCommand::NextBuffer),
+            ExCommand::Prev => self.process_command(EditorCommand::PrevBuffer),
+            ExCommand::Buffers => self.process_command(EditorCommand::ListBuffers),
+            ExCommand::Close => self.process_command(EditorCommand::CloseBuffer),
+            ExCommand::Split => self.process_command(EditorCommand::Split(SplitDirection::Horizontal)),
+            ExCommand::VSplit => self.process_command(EditorCommand::Split(SplitDirection::Vertical)),
+            ExCommand::Only => {
+                self.layout.only();
+                self.mark_all_panes_redraw();
+            }
+            ExCommand::Quit => self.process_command(EditorCommand::Quit),
+        }
+    }
+
+    /// 修改编辑器选项
+    fn set_option(&mut self, option: &str, value: &str) {
+        match option {
+            "tabwidth" => match value.parse::<usize>() {
+                Ok(tab_width) if tab_width > 0 => self.update_settings(|settings| settings.tab_width = tab_width),
+                _ => self.message_bar.update_message(&format!("Invalid tab width: {value}")),
+            },
+            "numbers" => match parse_flag(value) {
+                Some(flag) => self.update_settings(|settings| settings.line_numbers = flag),
+                None => self.message_bar.update_message(&format!("Invalid value for numbers: {value}")),
+            },
+            "wrap" => match parse_flag(value) {
+                Some(flag) => self.update_settings(|settings| settings.soft_wrap = flag),
+                None => self.message_bar.update_message(&format!("Invalid value for wrap: {value}")),
+            },
+            "autoclose" => match parse_flag(value) {
+                Some(flag) => self.update_settings(|settings| settings.auto_close = flag),
+                None => self.message_bar.update_message(&format!("Invalid value for autoclose: {value}")),
+            },
+            "whitespace" => match parse_flag(value) {
+                Some(flag) => self.update_settings(|settings| settings.show_whitespace = flag),
+                None => self.message_bar.update_message(&format!("Invalid value for whitespace: {value}")),
+            },
+            "tabbar" => match parse_flag(value) {
+                Some(flag) => self.show_tab_bar = flag,
+                None => self.message_bar.update_message(&format!("Invalid value for tabbar: {value}")),
+            },
+            _ => self.message_bar.update_message(&format!("Unknown option: {option}")),
+        }
+        self.mark_all_panes_redraw();
+    }
+
+    /// 修改当前buffer的设置，共享该buffer的窗格都会使用新的设置
+    fn update_settings(&mut self, update: impl FnOnce(&mut Settings)) {
+        if let Some(pane) = self.layout.focused_pane_mut() {
+            let mut settings = pane.view.settings();
+            update(&mut settings);
+            pane.view.set_settings(settings);
+        }
+    }
+
+    /// 将用户输入的路径解析为buffer使用的文件名，工作目录下的文件使用相对路径
+    fn resolve_file_name(&self, input: &str) -> String {
+        let path = resolve_path(input, &self.working_dir);
+        path.strip_prefix(&self.working_dir)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /// 打开文件并在当前窗格中显示，文件已经打开时直接切换到对应buffer
+    fn open_file(&mut self, input: &str) {
+        if input.is_empty() {
+            return;
+        }
+        let path = self.resolve_file_name(input);
+        let identity = file_identity(input, &self.working_dir);
+        if let Some(index) = self
+            .views
+            .iter()
+            .position(|view| view.file_name().is_some_and(|name| file_identity(&name, &self.working_dir) == identity))
+        {
+            self.show_buffer(index);
+            return;
+        }
+        let (settings, errors) = self.config.settings_for(Some(&path));
+        Self::report_config_errors(&mut self.message_bar, &errors);
+        let mut view = View::default();
+        if let Err(err) = view.load(&path, settings) {
+            self.message_bar.update_message(&format!("ERR: Could not open {path}: {err}"));
+            return;
+        }
+        self.views.push(view);
+        self.show_buffer(self.views.len().saturating_sub(1));
+    }
+
+    /// 鼠标点击标签时切换到对应buffer，点击窗格时将焦点移动到该窗格
+    fn handle_click(&mut self, position: Position) {
+        if self.show_tab_bar && position.row == 0 {
+            if let Some(buffer_index) = self.tab_bar.buffer_at(position.col) {
+                self.show_buffer(buffer_index);
+            }
+            return;
+        }
+        if self.layout.focus_at(position, self.pane_area()) {
+            self.on_focus_changed();
+        }
+    }
+
+    /// 焦点切换后，保证新窗格的光标仍然合法，并重新渲染所有状态行
+    fn on_focus_changed(&mut self) {
+        if let Some(pane) = self.layout.focused_pane_mut() {
+            pane.view.snap_to_valid_location();
+        }
+        self.mark_all_panes_redraw();
+    }
+
+    /// 标记所有窗格需要重新渲染
+    fn mark_all_panes_redraw(&mut self) {
+        for pane in self.layout.panes_mut() {
+            pane.view.mark_redraw();
+        }
+    }
+
+    /// terminal大小发生变化，所有窗格在下次渲染时重新计算区域
+    fn resize(&mut self, size: Size) {
+        self.terminal_size = size;
+        self.mark_all_panes_redraw();
+        self.message_bar.mark_redraw();
+    }
+
+    /// 退出Editor，有未保存的buffer时需要连续按下多次
+    fn handle_quit(&mut self) {
+        let modified = self.views.iter().filter(|view| view.is_modified()).count();
+        if modified == 0 || self.quit_times == 1 {
+            self.should_quit = true;
+            return;
+        }
+        self.quit_times = self.quit_times.saturating_sub(1);
+        let hint = self.repeat_hint(EditorCommand::Quit, self.quit_times);
+        self.message_bar.update_message(&format!(
+            "WARNING! {modified} buffer(s) have unsaved changes. {hint} to quit."
+        ));
+    }
+
+    /// 提示需要再执行几次命令，命令绑定了按键时显示该按键
+    fn repeat_hint(&self, command: EditorCommand, times: u8) -> String {
+        match self.keymap.keys_for(command) {
+            Some(keys) => format!("Press {keys} {times} more times"),
+            None => format!("Repeat the command {times} more times"),
+        }
+    }
+
+    /// 保存当前窗格中的buffer，没有文件名时询问文件名
+    fn handle_save(&mut self) {
+        if self.layout.focused_pane().is_some_and(|pane| pane.view.file_name().is_none()) {
+            self.open_save_as_prompt();
+            return;
+        }
+        let Some(pane) = self.layout.focused_pane_mut() else {
+            return;
+        };
+        let message = match pane.view.save() {
+            Ok(()) => format!("Saved {}", Self::buffer_name(&pane.view)),
+            Err(err) => format!("ERR: Could not save {}: {err}", Self::buffer_name(&pane.view)),
+        };
+        self.message_bar.update_message(&message);
+        // 保存时可能删除行末空白和末尾的空行
+        self.mark_buffer_redraw();
+        self.mark_all_panes_redraw();
+    }
+
+    /// 打开另存为输入框，以当前文件名作为初始内容
+    fn open_save_as_prompt(&mut self) {
+        let initial = self
+            .layout
+            .focused_pane()
+            .and_then(|pane| pane.view.file_name())
+            .unwrap_or_default();
+        self.save_as_prompt.open(&initial);
+        self.active_prompt = Some(PromptKind::SaveAs);
+    }
+
+    /// 以新文件名保存当前buffer，文件类型根据新文件名重新识别
+    fn save_as(&mut self, input: &str) {
+        if input.is_empty() {
+            return;
+        }
+        let file_name = self.resolve_file_name(input);
+        // 新文件名可能对应不同的语言设置和项目配置
+        let (settings, errors) = self.config.settings_for(Some(&file_name));
+        Self::report_config_errors(&mut self.message_bar, &errors);
+        let Some(pane) = self.layout.focused_pane_mut() else {
+            return;
+        };
+        // 写入失败时恢复原来的设置，buffer仍然对应原来的文件
+        let previous = pane.view.settings();
+        pane.view.set_settings(settings);
+        let message = match pane.view.save_as(&file_name) {
+            Ok(()) => format!("Saved {file_name} ({})", pane.view.file_type()),
+            Err(err) => {
+                pane.view.set_settings(previous);
+                format!("ERR: Could not save {file_name}: {err}")
+            }
+        };
+        self.message_bar.update_message(&message);
+        self.mark_buffer_redraw();
+        self.mark_all_panes_redraw();
+    }
+
+    /// 当前窗格显示的buffer下标
+    fn current_buffer(&self) -> usize {
+        self.layout.focused_pane().map_or(0, |pane| pane.buffer_index)
+    }
+
+    /// 循环切换当前窗格中的buffer，forward为true时切换到下一个，否则切换到上一个
+    fn switch_buffer(&mut self, forward: bool) {
+        let count = self.views.len();
+        if count <= 1 {
+            self.message_bar.update_message("No other buffers");
+            return;
+        }
+        let current = self.current_buffer();
+        let next = if forward {
+            current.saturating_add(1).checked_rem(count).unwrap_or(0)
+        } else {
+            current.checked_sub(1).unwrap_or(count.saturating_sub(1))
+        };
+        self.show_buffer(next);
+    }
+
+    /// 在当前窗格中显示指定buffer
+    /// 离开的buffer记录当前窗格的光标位置，切换到的buffer恢复上次记录的光标位置
+    fn show_buffer(&mut self, buffer_index: usize) {
+        let Some(pane) = self.layout.focused_pane_mut() else {
+            return;
+        };
+        if let Some(view) = self.views.get_mut(pane.buffer_index) {
+            *view = pane.view.clone();
+        }
+        if let Some(view) = self.views.get(buffer_index) {
+            pane.view = view.clone();
+            pane.buffer_index = buffer_index;
+            pane.view.snap_to_valid_location();
+            pane.view.mark_redraw();
+        }
+        let message = format!(
+            "[{}/{}] {}",
+            buffer_index.saturating_add(1),
+            self.views.len(),
+            Self::buffer_name(&pane.view)
+        );
+        self.message_bar.update_message(&message);
+        self.mark_all_panes_redraw();
+    }
+
+    /// 在消息栏中列出所有打开的buffer，当前buffer用*标记，未保存的buffer用+标记
+    fn list_buffers(&mut self) {
+        let current = self.current_buffer();
+        let list: Vec<String> = self
+            .views
+            .iter()
+            .enumerate()
+            .map(|(index, view)| {
+                let marker = if index == current { "*" } else { "" };
+                let modified = if view.is_modified() { "+" } else { "" };
+                format!("{}:{marker}{}{modified}", index.saturating_add(1), Self::buffer_name(view))
+            })
+            .collect();
+        self.message_bar.update_message(&list.join(" "));
+    }
+
+    /// 关闭当前buffer，有未保存修改时需要连续按下多次；关闭最后一个buffer时退出Editor
+    /// 所有显示该buffer的窗格切换到相邻的buffer
+    fn handle_close_buffer(&mut self) {
+        let closed = self.current_buffer();
+        let Some(view) = self.views.get(closed) else {
+            return;
+        };
+        if view.is_modified() && self.close_times > 1 {
+            self.close_times = self.close_times.saturating_sub(1);
+            let message = format!(
+                "WARNING! {} has unsaved changes. {} to close.",
+                Self::buffer_name(view),
+                self.repeat_hint(EditorCommand::CloseBuffer, self.close_times)
+            );
+            self.message_bar.update_message(&message);
+            return;
+        }
+        self.close_times = QUIT_TIMES;
+        self.views.remove(closed);
+        if self.views.is_empty() {
+            self.should_quit = true;
+            return;
+        }
+        let replacement = closed.min(self.views.len().saturating_sub(1));
+        for pane in self.layout.panes_mut() {
+            if pane.buffer_index == closed {
+                if let Some(view) = self.views.get(replacement) {
+                    pane.view = view.clone();
+                    pane.buffer_index = replacement;
                 }
+            } else if pane.buffer_index > closed {
+                pane.buffer_index = pane.buffer_index.saturating_sub(1);
             }
         }
+        self.show_buffer(replacement);
+    }
+
+    /// buffer显示名称
+    fn buffer_name(view: &View) -> String {
+        view.file_name().unwrap_or_else(|| "[No Name]".to_string())
+    }
+}
+
+/// 解析开关选项的值
+fn parse_flag(value: &str) -> Option<bool> {
+    match value {
+        "on" | "true" => Some(true),
+        "off" | "false" => Some(false),
+        _ => None,
     }
 }
 
@@ -104,4 +950,4 @@ impl Drop for Editor {
             let _ = Terminal::print("Goodbye. \r\n");
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,18 @@
+use std::ops::Range;
+
+/// 文本中需要以特殊样式显示的部分
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnnotationType {
+    Selection, // 选中的文本
+    MatchingBracket, // 光标处的括号以及与之匹配的括号
+    Caret, // 主光标之外的其他光标
+    Whitespace, // 显示空白时的空白字符
+    TrailingWhitespace, // 显示空白时的行末空白
+}
+
+/// 一个注释，range为行中的显示列范围
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Annotation {
+    pub range: Range<usize>,
+    pub annotation_type: AnnotationType,
+}
@@ -0,0 +1,267 @@
+use crossterm::event::{KeyCode, KeyEvent};
+
+use super::{prompt::{Prompt, PromptEvent}, terminal::Position};
+
+/// 命令行中可用的命令名称及参数说明
+const COMMANDS: &[(&str, &str)] = &[
+    ("save", ""),
+    ("saveas", "<path>"),
+    ("open", "<path>"),
+    ("goto", "<line>"),
+    ("set", "<option> <value>"),
+    ("replace", "<from> <to>"),
+    ("next", ""),
+    ("prev", ""),
+    ("buffers", ""),
+    ("close", ""),
+    ("split", ""),
+    ("vsplit", ""),
+    ("only", ""),
+    ("quit", ""),
+];
+
+/// 命令行提示符
+const PROMPT: &str = ":";
+
+/// 解析后的命令
+pub enum ExCommand {
+    Save,
+    SaveAs(String),
+    Open(String),
+    Goto(usize),
+    Set(String, String),
+    Replace(String, String),
+    Next,
+    Prev,
+    Buffers,
+    Close,
+    Split,
+    VSplit,
+    Only,
+    Quit,
+}
+
+impl TryFrom<&str> for ExCommand {
+    type Error = String;
+
+    /// 将用户输入的文本解析为命令，命令名称需要完整或者是唯一的前缀，模糊匹配只用于补全
+    /// 最后一个参数为该行剩余的全部内容，参数可以用双引号括起以包含空格
+    fn try_from(input: &str) -> Result<Self, Self::Error> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Err("Empty command".to_string());
+        }
+        let (name, rest) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+        let name = resolve_command(name)?;
+        let hint = COMMANDS
+            .iter()
+            .find(|(command, _)| *command == name)
+            .map_or("", |(_, hint)| *hint);
+        let usage = || format!("Usage: {name} {hint}");
+        let args = parse_args(rest, hint.matches('<').count()).ok_or_else(usage)?;
+        match (name, args.as_slice()) {
+            ("save", []) => Ok(Self::Save),
+            ("saveas", [path]) => Ok(Self::SaveAs(path.clone())),
+            ("open", [path]) => Ok(Self::Open(path.clone())),
+            ("goto", [line]) => line
+                .parse()
+                .map(Self::Goto)
+                .map_err(|_| format!("Invalid line number: {line}")),
+            ("set", [option, value]) => Ok(Self::Set(option.clone(), value.clone())),
+            ("replace", [from, to]) => Ok(Self::Replace(from.clone(), to.clone())),
+            ("next", []) => Ok(Self::Next),
+            ("prev", []) => Ok(Self::Prev),
+            ("buffers", []) => Ok(Self::Buffers),
+            ("close", []) => Ok(Self::Close),
+            ("split", []) => Ok(Self::Split),
+            ("vsplit", []) => Ok(Self::VSplit),
+            ("only", []) => Ok(Self::Only),
+            ("quit", []) => Ok(Self::Quit),
+            _ => Err(usage()),
+        }
+    }
+}
+
+/// 由输入的名称确定命令，名称需要完整或者是唯一的前缀
+fn resolve_command(name: &str) -> Result<&'static str, String> {
+    if let Some((command, _)) = COMMANDS.iter().find(|(command, _)| *command == name) {
+        return Ok(command);
+    }
+    let candidates: Vec<&str> = COMMANDS
+        .iter()
+        .map(|(command, _)| *command)
+        .filter(|command| command.starts_with(name))
+        .collect();
+    match candidates.as_slice() {
+        [] => Err(format!("Unknown command: {name}")),
+        [command] => Ok(command),
+        _ => Err(format!("Ambiguous command: {name} ({})", candidates.join(", "))),
+    }
+}
+
+/// 将命令名称之后的文本解析为`count`个参数，参数个数不符或引号不完整时返回None
+/// 参数以空白分隔，最后一个参数为剩余的全部内容；以双引号开头的参数到下一个双引号结束，其中可以用\转义
+fn parse_args(input: &str, count: usize) -> Option<Vec<String>> {
+    let mut args = Vec::with_capacity(count);
+    let mut rest = input.trim();
+    for index in 0..count {
+        if rest.is_empty() {
+            return None;
+        }
+        let (arg, remaining) = if let Some(quoted) = rest.strip_prefix('"') {
+            unquote(quoted)?
+        } else if index.saturating_add(1) == count {
+            (rest.to_string(), "")
+        } else {
+            let (word, remaining) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            (word.to_string(), remaining)
+        };
+        args.push(arg);
+        rest = remaining.trim_start();
+    }
+    rest.is_empty().then_some(args)
+}
+
+/// 解析开头的双引号之后的内容，返回引号中的文本和结束引号之后的剩余内容
+fn unquote(input: &str) -> Option<(String, &str)> {
+    let mut arg = String::new();
+    let mut chars = input.char_indices();
+    while let Some((index, ch)) = chars.next() {
+        match ch {
+            '"' => return Some((arg, input.get(index.saturating_add(1)..)?)),
+            '\\' => arg.push(chars.next().map_or('\\', |(_, escaped)| escaped)),
+            _ => arg.push(ch),
+        }
+    }
+    None
+}
+
+/// 对命令名称进行模糊匹配，按得分从高到低返回匹配的命令，用于Tab补全
+/// 输入中的字符需要按顺序出现在命令名称中，前缀匹配和连续匹配得分更高
+fn fuzzy_matches(pattern: &str) -> Vec<&'static str> {
+    let mut scored: Vec<(usize, &str)> = COMMANDS
+        .iter()
+        .filter_map(|(command, _)| fuzzy_score(pattern, command).map(|score| (score, *command)))
+        .collect();
+    scored.sort_by(|(a, _), (b, _)| b.cmp(a));
+    scored.into_iter().map(|(_, command)| command).collect()
+}
+
+/// 计算模糊匹配得分，不匹配时返回None
+fn fuzzy_score(pattern: &str, candidate: &str) -> Option<usize> {
+    let mut score: usize = 0;
+    let mut candidate_chars = candidate.chars().enumerate();
+    let mut last_match: Option<usize> = None;
+    for pattern_char in pattern.chars() {
+        let (index, _) = candidate_chars.find(|(_, ch)| ch.eq_ignore_ascii_case(&pattern_char))?;
+        score = score.saturating_add(1);
+        if index == 0 {
+            score = score.saturating_add(3);
+        }
+        if last_match.is_some_and(|last| last.saturating_add(1) == index) {
+            score = score.saturating_add(2);
+        }
+        last_match = Some(index);
+    }
+    Some(score)
+}
+
+/// 底部命令行，输入命令名称和参数，支持Tab补全命令名称和上下键浏览历史命令
+pub struct CommandLine {
+    prompt: Prompt, // 输入框
+    completions: Vec<&'static str>, // 上次Tab补全的候选命令
+    completion_index: usize, // 当前补全的候选命令下标
+}
+
+impl CommandLine {
+    /// 打开命令行，清空上次的输入
+    pub fn open(&mut self) {
+        self.prompt.reset();
+        self.completions.clear();
+    }
+
+    /// 处理命令行中的按键，Tab时补全命令名称
+    pub fn handle_key(&mut self, key: KeyEvent) -> PromptEvent {
+        // 非Tab按键结束补全的循环
+        if key.code != KeyCode::Tab {
+            self.completions.clear();
+        }
+        let event = self.prompt.handle_key(key);
+        if matches!(event, PromptEvent::Complete) {
+            self.complete();
+        }
+        event
+    }
+
+    /// 补全命令名称，连续按下Tab时在候选命令之间循环
+    fn complete(&mut self) {
+        let input = self.prompt.value();
+        if input.contains(' ') {
+            return;
+        }
+        if self.completions.is_empty() {
+            self.completions = fuzzy_matches(&input);
+            self.completion_index = 0;
+        } else {
+            self.completion_index = self
+                .completion_index
+                .saturating_add(1)
+                .checked_rem(self.completions.len())
+                .unwrap_or(0);
+        }
+        if let Some(command) = self.completions.get(self.completion_index) {
+            // 唯一候选时补全后添加空格以便输入参数，多个候选时再次按下Tab继续循环
+            if self.completions.len() > 1 {
+                self.prompt.set_value(command);
+            } else {
+                self.prompt.set_value(&format!("{command} "));
+            }
+        }
+    }
+
+    /// 在指定行渲染命令行，有补全候选时在输入之后显示
+    pub fn render(&mut self, at: usize, width: usize) {
+        let suffix = if self.completions.len() > 1 {
+            format!("  ({})", self.completions.join(", "))
+        } else {
+            String::new()
+        };
+        self.prompt.render(at, width, &suffix);
+    }
+
+    /// 光标在terminal中的位置
+    pub fn caret_position(&self, at: usize) -> Position {
+        self.prompt.caret_position(at)
+    }
+}
+
+impl Default for CommandLine {
+    fn default() -> Self {
+        Self {
+            prompt: Prompt::new(PROMPT),
+            completions: Vec::new(),
+            completion_index: 0,
+        }
+    }
+}
+
+#[test]
+fn test_fuzzy_matches() {
+    assert_eq!(fuzzy_matches("rp").first(), Some(&"replace"));
+    assert_eq!(fuzzy_matches("vs").first(), Some(&"vsplit"));
+    assert!(fuzzy_matches("xyz").is_empty());
+    assert!(matches!(ExCommand::try_from("got 42"), Ok(ExCommand::Goto(42))));
+    assert!(ExCommand::try_from("goto x").is_err());
+    // 模糊匹配和有歧义的前缀不执行命令
+    assert!(ExCommand::try_from("rp a b").is_err());
+    assert!(ExCommand::try_from("s").is_err());
+    assert!(ExCommand::try_from("o").is_err());
+    assert!(matches!(ExCommand::try_from("save"), Ok(ExCommand::Save)));
+    assert!(matches!(ExCommand::try_from("op my file.txt"), Ok(ExCommand::Open(path)) if path == "my file.txt"));
+    assert!(matches!(
+        ExCommand::try_from(r#"replace "a \"b\"" c  d "#),
+        Ok(ExCommand::Replace(from, to)) if from == r#"a "b""# && to == "c  d"
+    ));
+    assert!(matches!(ExCommand::try_from(r#"replace x " ""#), Ok(ExCommand::Replace(_, to)) if to == " "));
+    assert!(ExCommand::try_from(r#"replace "a b"#).is_err());
+}
@@ -0,0 +1,346 @@
+use std::{
+    env,
+    fs::read_to_string,
+    io::ErrorKind,
+    path::{self, Path, PathBuf},
+};
+
+use super::editorconfig;
+
+/// 全局配置文件名，位于用户配置目录中
+const CONFIG_FILE: &str = "config.toml";
+
+/// 项目配置文件名，从打开的文件所在目录向上查找
+const PROJECT_CONFIG_FILE: &str = ".hecto.toml";
+
+/// 默认制表符宽度
+pub const DEFAULT_TAB_WIDTH: usize = 4;
+
+/// 文件使用的换行符
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    /// 换行符对应的字符串
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::Crlf => "\r\n",
+        }
+    }
+}
+
+/// 缩进使用的字符
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum IndentStyle {
+    #[default]
+    Spaces,
+    Tabs,
+}
+
+/// 保存文件时使用的编码
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Charset {
+    Utf8,
+    Utf8Bom, // 文件开头带有BOM的UTF-8
+}
+
+/// 应用到一个buffer的设置，由默认值、全局配置和项目配置依次覆盖得到
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Settings {
+    pub tab_width: usize, // 制表符宽度
+    pub indent_style: IndentStyle, // 缩进使用空格还是制表符
+    pub indent_size: usize, // 每一级缩进的宽度
+    pub line_numbers: bool, // 是否显示行号
+    pub soft_wrap: bool, // 是否自动换行显示超出view宽度的行
+    pub line_ending: Option<LineEnding>, // 保存时使用的换行符，None表示保持文件原有的换行符
+    pub insert_final_newline: bool, // 保存时最后一行之后是否添加换行符
+    pub trim_trailing_whitespace: bool, // 保存时是否删除行末空白
+    pub trim_final_newlines: bool, // 保存时是否删除文件末尾的空行，与insert_final_newline一起保证文件以一个换行符结尾
+    pub charset: Option<Charset>, // 保存时使用的编码，None表示保持文件原有的BOM
+    pub auto_close: bool, // 输入开括号和引号时是否自动插入对应的闭括号和引号
+    pub show_whitespace: bool, // 是否以可见符号显示空格、制表符等空白字符
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            tab_width: DEFAULT_TAB_WIDTH,
+            indent_style: IndentStyle::default(),
+            indent_size: DEFAULT_TAB_WIDTH,
+            line_numbers: false,
+            soft_wrap: false,
+            line_ending: None,
+            insert_final_newline: true,
+            trim_trailing_whitespace: false,
+            trim_final_newlines: false,
+            charset: None,
+            auto_close: true,
+            show_whitespace: false,
+        }
+    }
+}
+
+impl Settings {
+    /// 将配置表中的设置应用到当前设置，无效的设置项被跳过并记录错误
+    fn apply(&mut self, table: &toml::Table, section: &str, errors: &mut Vec<String>) {
+        for (key, value) in table {
+            let result = match key.as_str() {
+                "tab_width" => match value.as_integer().and_then(|width| usize::try_from(width).ok()) {
+                    Some(tab_width) if tab_width > 0 => {
+                        self.tab_width = tab_width;
+                        Ok(())
+                    }
+                    _ => Err("expected a positive integer"),
+                },
+                "indent_size" => match value.as_integer().and_then(|size| usize::try_from(size).ok()) {
+                    Some(indent_size) if indent_size > 0 => {
+                        self.indent_size = indent_size;
+                        Ok(())
+                    }
+                    _ => Err("expected a positive integer"),
+                },
+                "indent_style" => match value.as_str() {
+                    Some("spaces") => {
+                        self.indent_style = IndentStyle::Spaces;
+                        Ok(())
+                    }
+                    Some("tabs") => {
+                        self.indent_style = IndentStyle::Tabs;
+                        Ok(())
+                    }
+                    _ => Err("expected \"spaces\" or \"tabs\""),
+                },
+                "line_numbers" => value.as_bool().map(|flag| self.line_numbers = flag).ok_or("expected a boolean"),
+                "soft_wrap" => value.as_bool().map(|flag| self.soft_wrap = flag).ok_or("expected a boolean"),
+                "auto_close" => value.as_bool().map(|flag| self.auto_close = flag).ok_or("expected a boolean"),
+                "show_whitespace" => value.as_bool().map(|flag| self.show_whitespace = flag).ok_or("expected a boolean"),
+                "insert_final_newline" => value
+                    .as_bool()
+                    .map(|flag| self.insert_final_newline = flag)
+                    .ok_or("expected a boolean"),
+                "trim_trailing_whitespace" => value
+                    .as_bool()
+                    .map(|flag| self.trim_trailing_whitespace = flag)
+                    .ok_or("expected a boolean"),
+                "trim_final_newlines" => value
+                    .as_bool()
+                    .map(|flag| self.trim_final_newlines = flag)
+                    .ok_or("expected a boolean"),
+                "charset" => match value.as_str() {
+                    Some("utf-8") => {
+                        self.charset = Some(Charset::Utf8);
+                        Ok(())
+                    }
+                    Some("utf-8-bom") => {
+                        self.charset = Some(Charset::Utf8Bom);
+                        Ok(())
+                    }
+                    _ => Err("expected \"utf-8\" or \"utf-8-bom\""),
+                },
+                "line_ending" => match value.as_str() {
+                    Some("lf") => {
+                        self.line_ending = Some(LineEnding::Lf);
+                        Ok(())
+                    }
+                    Some("crlf") => {
+                        self.line_ending = Some(LineEnding::Crlf);
+                        Ok(())
+                    }
+                    _ => Err("expected \"lf\" or \"crlf\""),
+                },
+                _ => Err("unknown setting"),
+            };
+            if let Err(err) = result {
+                errors.push(format!("[{section}] {key}: {err}"));
+            }
+        }
+    }
+}
+
+/// 一个配置文件的内容
+/// [editor]表为所有文件的设置，[languages.<扩展名>]表为对应类型文件的设置，[terminal]表为与终端相关的设置
+#[derive(Default)]
+struct ConfigFile {
+    editor: toml::Table, // 所有文件的设置
+    languages: toml::Table, // 按文件扩展名区分的设置
+    terminal: toml::Table, // 与终端相关的设置，只在全局配置中有效
+}
+
+impl ConfigFile {
+    /// 读取配置文件，文件不存在时返回None
+    fn load(path: &Path) -> Result<Option<Self>, String> {
+        match read_to_string(path) {
+            Ok(contents) => Self::parse(&contents).map(Some),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(format!("Could not read {}: {err}", path.display())),
+        }
+    }
+
+    /// 解析配置文件内容，只检查表的结构，设置项在应用时检查
+    fn parse(contents: &str) -> Result<Self, String> {
+        let table = contents
+            .parse::<toml::Table>()
+            .map_err(|err| format!("invalid TOML: {}", err.message()))?;
+        let mut config = Self::default();
+        for (key, value) in table {
+            let Some(section) = value.as_table() else {
+                return Err(format!("'{key}' must be a table"));
+            };
+            match key.as_str() {
+                "editor" => config.editor = section.clone(),
+                "languages" => config.languages = section.clone(),
+                "terminal" => config.terminal = section.clone(),
+                _ => return Err(format!("unknown section '{key}'")),
+            }
+        }
+        Ok(config)
+    }
+
+    /// 依次应用[editor]和扩展名对应的[languages]设置
+    fn apply(&self, settings: &mut Settings, extension: Option<&str>, errors: &mut Vec<String>) {
+        settings.apply(&self.editor, "editor", errors);
+        let Some(extension) = extension else {
+            return;
+        };
+        match self.languages.get(extension).map(toml::Value::as_table) {
+            Some(Some(table)) => settings.apply(table, &format!("languages.{extension}"), errors),
+            Some(None) => errors.push(format!("'languages.{extension}' must be a table")),
+            None => (),
+        }
+    }
+
+    /// 检查所有设置项，返回错误信息
+    fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        self.apply(&mut Settings::default(), None, &mut errors);
+        for extension in self.languages.keys() {
+            self.apply(&mut Settings::default(), Some(extension), &mut errors);
+        }
+        for (key, value) in &self.terminal {
+            match key.as_str() {
+                "probe_widths" if value.is_bool() => (),
+                "probe_widths" => errors.push(format!("[terminal] {key}: expected a boolean")),
+                _ => errors.push(format!("[terminal] {key}: unknown setting")),
+            }
+        }
+        // [editor]的错误在检查每种语言时重复出现，只保留一次
+        errors.sort();
+        errors.dedup();
+        errors
+    }
+}
+
+/// 编辑器配置，由用户配置目录中的全局配置文件和项目中的`.hecto.toml`组成
+#[derive(Default)]
+pub struct Config {
+    global: ConfigFile, // 全局配置
+}
+
+impl Config {
+    /// 读取全局配置文件，返回配置以及配置文件中的错误信息
+    pub fn load() -> (Self, Vec<String>) {
+        let Some(path) = config_dir().map(|dir| dir.join(CONFIG_FILE)) else {
+            return (Self::default(), Vec::new());
+        };
+        match ConfigFile::load(&path) {
+            Ok(Some(global)) => {
+                let errors = global
+                    .validate()
+                    .into_iter()
+                    .map(|err| format!("{CONFIG_FILE}: {err}"))
+                    .collect();
+                (Self { global }, errors)
+            }
+            Ok(None) => (Self::default(), Vec::new()),
+            Err(err) => (Self::default(), vec![format!("{CONFIG_FILE}: {err}")]),
+        }
+    }
+
+    /// 是否在启动时探测终端显示emoji序列的实际宽度
+    pub fn probe_widths(&self) -> bool {
+        self.global
+            .terminal
+            .get("probe_widths")
+            .and_then(toml::Value::as_bool)
+            .unwrap_or(false)
+    }
+
+    /// 获取文件适用的设置，没有文件名时只应用全局配置
+    /// 依次应用全局配置、`.editorconfig`和项目配置，项目配置为从文件所在目录向上找到的第一个`.hecto.toml`
+    pub fn settings_for(&self, file_name: Option<&str>) -> (Settings, Vec<String>) {
+        let mut settings = Settings::default();
+        let mut errors = Vec::new();
+        let extension = file_name.and_then(|file_name| {
+            Path::new(file_name)
+                .extension()
+                .map(|extension| extension.to_string_lossy().to_lowercase())
+        });
+        // 全局配置的错误在启动时已经报告过
+        self.global.apply(&mut settings, extension.as_deref(), &mut Vec::new());
+        if let Some(file_name) = file_name {
+            errors.extend(editorconfig::apply(&mut settings, file_name));
+        }
+        if let Some(path) = file_name.and_then(find_project_config) {
+            let mut project_errors = Vec::new();
+            match ConfigFile::load(&path) {
+                Ok(Some(project)) => project.apply(&mut settings, extension.as_deref(), &mut project_errors),
+                Ok(None) => (),
+                Err(err) => project_errors.push(err),
+            }
+            errors.extend(project_errors.into_iter().map(|err| format!("{}: {err}", path.display())));
+        }
+        (settings, errors)
+    }
+}
+
+/// 从文件所在目录开始向上查找项目配置文件
+fn find_project_config(file_name: &str) -> Option<PathBuf> {
+    let path = path::absolute(file_name).ok()?;
+    path.ancestors()
+        .skip(1)
+        .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+        .find(|config| config.is_file())
+}
+
+/// 用户配置目录，优先使用`$XDG_CONFIG_HOME`，否则使用`~/.config`
+pub fn config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("hecto"))
+}
+
+#[test]
+fn test_settings_layers() {
+    let global = ConfigFile::parse(
+        r#"
+        [editor]
+        tab_width = 8
+        line_numbers = true
+
+        [languages.py]
+        tab_width = 2
+        soft_wrap = "yes"
+        "#,
+    )
+    .unwrap_or_default();
+    let mut errors = Vec::new();
+    let mut settings = Settings::default();
+    global.apply(&mut settings, Some("rs"), &mut errors);
+    assert_eq!(settings.tab_width, 8);
+    assert!(settings.line_numbers);
+    assert!(errors.is_empty());
+
+    let mut settings = Settings::default();
+    global.apply(&mut settings, Some("py"), &mut errors);
+    assert_eq!(settings.tab_width, 2);
+    assert_eq!(errors, vec!["[languages.py] soft_wrap: expected a boolean"]);
+    assert!(ConfigFile::parse("[colors]").is_err());
+}
@@ -1,8 +1,12 @@
-use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{Event, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
 use std::convert::TryFrom;
 
-use super::terminal::Size;
+use super::terminal::{Position, Size};
 
+/// 每次调整窗格大小的百分比
+const PANE_RESIZE_STEP: isize = 5;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Direction {
     PageUP,
     PageDown,
@@ -12,46 +16,207 @@ pub enum Direction {
     Down,
     Left,
     Right,
+    NextWordStart, // 下一个单词的开头
+    NextWordStartInLine, // 下一个单词的开头，下一个单词在之后的行时停在当前行的行末
+    PrevWordStart, // 上一个单词的开头
+    WordEnd,       // 单词的结尾
+    WordLeft,      // 按Unicode单词边界移动到上一个单词的开头
+    WordRight,     // 按Unicode单词边界移动到下一个单词的结尾
+    PrevParagraph, // 上一个以空行分隔的段落
+    NextParagraph, // 下一个以空行分隔的段落
+    DocumentStart, // 文件开头
+    DocumentEnd,   // 文件末尾
+    MatchingBracket, // 与光标处括号匹配的括号
+}
+
+/// 选择的方式
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SelectionKind {
+    Exclusive, // 不包含光标所在的字素
+    Inclusive, // 包含光标所在的字素
+    Lines,     // 选择涉及的整行
+    Block,     // 选择起点和光标的显示列之间的矩形，用于编辑对齐的表格
+}
+
+/// 窗格分割方向
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SplitDirection {
+    Horizontal, // 水平分割线，两个窗格上下排列
+    Vertical,   // 竖直分割线，两个窗格左右排列
+}
+
+/// 焦点在窗格之间移动的方向
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PaneDirection {
+    Up,
+    Down,
+    Left,
+    Right,
 }
 
 /// 自定义Editor命令
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum EditorCommand {
     Move(Direction),    // 移动
     Resize(Size),       // 窗口大小发生变化
     Quit,               // 退出
     Insert(char),       // 键盘输入字符
+    InsertNewline,      // 换行并自动缩进
+    Indent,             // 增加选中各行的缩进，没有选择多行时插入一级缩进
+    Outdent,            // 减少选中各行或当前行的缩进
+    ToggleComment,      // 切换当前行或选中各行的行注释
+    Undo,               // 撤销
+    Redo,               // 重做
     Backspace,          // 退格
     Delete,             // 删除
+    Save,               // 保存当前buffer
+    NextBuffer,         // 切换到下一个buffer
+    PrevBuffer,         // 切换到上一个buffer
+    ListBuffers,        // 列出所有打开的buffer
+    CloseBuffer,        // 关闭当前buffer
+    Split(SplitDirection), // 分割当前窗格
+    ClosePane,          // 关闭当前窗格
+    FocusPane(PaneDirection), // 将焦点移动到指定方向的窗格
+    FocusNextPane,      // 将焦点依次移动到下一个窗格
+    ResizePane(isize),  // 调整当前窗格大小，单位为百分比
+    ToggleTabBar,       // 显示或隐藏顶部标签栏
+    Click(Position),    // 鼠标左键点击
+    OpenCommandLine,    // 打开命令行
+    Search,             // 增量查找
+    SearchNext,         // 从光标处查找上次查找内容的下一个匹配
+    SearchPrev,         // 从光标处查找上次查找内容的上一个匹配
+    GotoLine,           // 跳转到指定行
+    SaveAs,             // 以新文件名保存
+    Open,               // 打开文件
+    StartSelection(SelectionKind), // 以光标位置为起点开始选择
+    ClearSelection,     // 取消选择
+    Copy,               // 复制选中的文本
+    Cut,                // 剪切选中的文本
+    Change,             // 剪切选中的文本，按行选择时保留一个空行
+    Paste,              // 在光标之前粘贴，按行复制的文本粘贴到当前行之前
+    PasteAfter,         // 在光标之后粘贴，按行复制的文本粘贴到当前行之后
+    JumpToLine(usize),  // 跳转到指定行（从1开始）
+    KillLine,           // 剪切到行末，光标在行末时剪切换行符
+    Yank,               // 在光标处插入kill ring中的当前条目
+    YankPop,            // 将刚插入的文本替换为kill ring中更早的条目
+    DeleteWordBackward, // 删除到上一个单词的开头
+    DeleteWordForward,  // 删除到下一个单词的结尾
+    AddCaretAbove,      // 在最上方光标的上一行添加光标
+    AddCaretBelow,      // 在最下方光标的下一行添加光标
+    AddNextOccurrence,  // 在选中文本或光标处单词的下一处出现添加光标
+    AddCaretAt(Position), // 在鼠标点击处添加光标，已有光标时移除
+    ToggleWhitespace,   // 切换是否显示空白字符
+}
+
+impl TryFrom<&str> for EditorCommand {
+    type Error = String;
+
+    /// 根据命令名称创建命令，用于从配置文件中读取按键绑定
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        let command = match name {
+            "quit" => Self::Quit,
+            "save" => Self::Save,
+            "save_as" => Self::SaveAs,
+            "open" => Self::Open,
+            "move_up" => Self::Move(Direction::Up),
+            "move_down" => Self::Move(Direction::Down),
+            "move_left" => Self::Move(Direction::Left),
+            "move_right" => Self::Move(Direction::Right),
+            "move_home" => Self::Move(Direction::Home),
+            "move_end" => Self::Move(Direction::End),
+            "page_up" => Self::Move(Direction::PageUP),
+            "page_down" => Self::Move(Direction::PageDown),
+            "backspace" => Self::Backspace,
+            "delete" => Self::Delete,
+            "insert_newline" => Self::InsertNewline,
+            "indent" => Self::Indent,
+            "insert_tab" => Self::Insert('\t'),
+            "outdent" => Self::Outdent,
+            "toggle_comment" => Self::ToggleComment,
+            "undo" => Self::Undo,
+            "redo" => Self::Redo,
+            "next_buffer" => Self::NextBuffer,
+            "prev_buffer" => Self::PrevBuffer,
+            "list_buffers" => Self::ListBuffers,
+            "close_buffer" => Self::CloseBuffer,
+            "split_horizontal" => Self::Split(SplitDirection::Horizontal),
+            "split_vertical" => Self::Split(SplitDirection::Vertical),
+            "close_pane" => Self::ClosePane,
+            "focus_next_pane" => Self::FocusNextPane,
+            "focus_up" => Self::FocusPane(PaneDirection::Up),
+            "focus_down" => Self::FocusPane(PaneDirection::Down),
+            "focus_left" => Self::FocusPane(PaneDirection::Left),
+            "focus_right" => Self::FocusPane(PaneDirection::Right),
+            "grow_pane" => Self::ResizePane(PANE_RESIZE_STEP),
+            "shrink_pane" => Self::ResizePane(-PANE_RESIZE_STEP),
+            "toggle_tab_bar" => Self::ToggleTabBar,
+            "command_line" => Self::OpenCommandLine,
+            "search" => Self::Search,
+            "search_next" => Self::SearchNext,
+            "search_prev" => Self::SearchPrev,
+            "goto_line" => Self::GotoLine,
+            "select" => Self::StartSelection(SelectionKind::Exclusive),
+            "select_block" => Self::StartSelection(SelectionKind::Block),
+            "clear_selection" => Self::ClearSelection,
+            "copy" => Self::Copy,
+            "cut" => Self::Cut,
+            "paste" => Self::Paste,
+            "paste_after" => Self::PasteAfter,
+            "kill_line" => Self::KillLine,
+            "yank" => Self::Yank,
+            "yank_pop" => Self::YankPop,
+            "next_word_start" => Self::Move(Direction::NextWordStart),
+            "prev_word_start" => Self::Move(Direction::PrevWordStart),
+            "word_end" => Self::Move(Direction::WordEnd),
+            "word_left" => Self::Move(Direction::WordLeft),
+            "word_right" => Self::Move(Direction::WordRight),
+            "prev_paragraph" => Self::Move(Direction::PrevParagraph),
+            "next_paragraph" => Self::Move(Direction::NextParagraph),
+            "document_start" => Self::Move(Direction::DocumentStart),
+            "document_end" => Self::Move(Direction::DocumentEnd),
+            "matching_bracket" => Self::Move(Direction::MatchingBracket),
+            "delete_word_backward" => Self::DeleteWordBackward,
+            "delete_word_forward" => Self::DeleteWordForward,
+            "add_caret_above" => Self::AddCaretAbove,
+            "add_caret_below" => Self::AddCaretBelow,
+            "add_next_occurrence" => Self::AddNextOccurrence,
+            "toggle_whitespace" => Self::ToggleWhitespace,
+            _ => return Err(format!("Unknown command: {name}")),
+        };
+        Ok(command)
+    }
 }
 
 #[allow(clippy::as_conversions)]
 impl TryFrom<Event> for EditorCommand {
     type Error = String;
 
-    /// 将crossterm中时间转换成自定义的EditorCommand
+    /// 将crossterm中时间转换成自定义的`EditorCommand`
+    /// 按键事件由`Keymap`根据按键绑定转换
     fn try_from(event: Event) -> Result<Self, Self::Error> {
         match event {
-            Event::Key(KeyEvent {
-                code, modifiers, ..
-            }) => match (code, modifiers) {
-                (KeyCode::Char('c'), KeyModifiers::CONTROL) => Ok(Self::Quit),
-                (KeyCode::Up, _) => Ok(Self::Move(Direction::Up)),
-                (KeyCode::Down, _) => Ok(Self::Move(Direction::Down)),
-                (KeyCode::Left, _) => Ok(Self::Move(Direction::Left)),
-                (KeyCode::Right, _) => Ok(Self::Move(Direction::Right)),
-                (KeyCode::Home, _) => Ok(Self::Move(Direction::Home)),
-                (KeyCode::End, _) => Ok(Self::Move(Direction::End)),
-                (KeyCode::PageUp, _) => Ok(Self::Move(Direction::PageUP)),
-                (KeyCode::PageDown, _) => Ok(Self::Move(Direction::PageDown)),
-                (KeyCode::Char(ch), KeyModifiers::NONE | KeyModifiers::SHIFT) => Ok(Self::Insert(ch)),
-                (KeyCode::Backspace, _) => Ok(Self::Backspace),
-                (KeyCode::Delete, _) => Ok(Self::Delete),
-                _ => Err(format!("Key Code not supported: {code:?}")),
-            },
             Event::Resize(width_u16, height_u16) => Ok(Self::Resize(Size {
                 height: height_u16 as usize,
                 width: width_u16 as usize,
             })),
+            Event::Mouse(MouseEvent {
+                kind: MouseEventKind::Down(MouseButton::Left),
+                column,
+                row,
+                modifiers,
+            }) if modifiers.contains(KeyModifiers::ALT) => Ok(Self::AddCaretAt(Position {
+                col: column as usize,
+                row: row as usize,
+            })),
+            Event::Mouse(MouseEvent {
+                kind: MouseEventKind::Down(MouseButton::Left),
+                column,
+                row,
+                ..
+            }) => Ok(Self::Click(Position {
+                col: column as usize,
+                row: row as usize,
+            })),
             _ => Err(format!("Event not supported: {event:?}")),
         }
     }
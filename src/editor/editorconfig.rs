@@ -0,0 +1,364 @@
+use std::{
+    collections::HashMap,
+    fs::read_to_string,
+    path::{self, Path},
+};
+
+use super::config::{Charset, IndentStyle, LineEnding, Settings};
+
+/// `.editorconfig`文件名
+const EDITORCONFIG_FILE: &str = ".editorconfig";
+
+/// glob中的一个匹配单元
+#[derive(Clone, Debug)]
+enum Token {
+    Literal(char), // 普通字符
+    AnyChar, // ?，匹配除/以外的任意一个字符
+    Star, // *，匹配不含/的任意字符串
+    DoubleStar, // **，匹配任意字符串
+    Class { negated: bool, ranges: Vec<(char, char)> }, // [abc]、[a-z]、[!abc]
+    Alternatives(Vec<Vec<Token>>), // {a,b,c}
+    NumberRange(i64, i64), // {n1..n2}，匹配范围内的整数
+}
+
+/// 将glob解析为匹配单元
+fn parse_glob(pattern: &[char]) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    while let Some(&ch) = pattern.get(index) {
+        index = index.saturating_add(1);
+        match ch {
+            '\\' => {
+                if let Some(&escaped) = pattern.get(index) {
+                    tokens.push(Token::Literal(escaped));
+                    index = index.saturating_add(1);
+                }
+            }
+            '*' if pattern.get(index) == Some(&'*') => {
+                tokens.push(Token::DoubleStar);
+                index = index.saturating_add(1);
+            }
+            '*' => tokens.push(Token::Star),
+            '?' => tokens.push(Token::AnyChar),
+            '[' => match parse_class(pattern, index) {
+                Some((token, end)) => {
+                    tokens.push(token);
+                    index = end;
+                }
+                None => tokens.push(Token::Literal('[')),
+            },
+            '{' => match find_closing_brace(pattern, index) {
+                Some(end) => {
+                    let inner = pattern.get(index..end).unwrap_or_default();
+                    tokens.extend(parse_braces(inner));
+                    index = end.saturating_add(1);
+                }
+                None => tokens.push(Token::Literal('{')),
+            },
+            _ => tokens.push(Token::Literal(ch)),
+        }
+    }
+    tokens
+}
+
+/// 解析[...]字符集合，start为[之后的位置，返回匹配单元和]之后的位置
+fn parse_class(pattern: &[char], start: usize) -> Option<(Token, usize)> {
+    let mut index = start;
+    let negated = pattern.get(index) == Some(&'!');
+    if negated {
+        index = index.saturating_add(1);
+    }
+    let mut ranges = Vec::new();
+    loop {
+        let &ch = pattern.get(index)?;
+        // 字符集合中不能出现路径分隔符
+        if ch == '/' {
+            return None;
+        }
+        if ch == ']' && !ranges.is_empty() {
+            return Some((Token::Class { negated, ranges }, index.saturating_add(1)));
+        }
+        let range_end = index.saturating_add(2);
+        match (pattern.get(index.saturating_add(1)), pattern.get(range_end)) {
+            (Some('-'), Some(&end)) if end != ']' => {
+                ranges.push((ch, end));
+                index = range_end.saturating_add(1);
+            }
+            _ => {
+                ranges.push((ch, ch));
+                index = index.saturating_add(1);
+            }
+        }
+    }
+}
+
+/// 找到与{匹配的}，start为{之后的位置
+fn find_closing_brace(pattern: &[char], start: usize) -> Option<usize> {
+    let mut depth: usize = 0;
+    let mut index = start;
+    while let Some(&ch) = pattern.get(index) {
+        match ch {
+            '\\' => index = index.saturating_add(1),
+            '{' => depth = depth.saturating_add(1),
+            '}' if depth == 0 => return Some(index),
+            '}' => depth = depth.saturating_sub(1),
+            _ => (),
+        }
+        index = index.saturating_add(1);
+    }
+    None
+}
+
+/// 解析{}中的内容，可以是数字范围或者由逗号分隔的多个选项，只有一个选项时按普通字符处理
+fn parse_braces(inner: &[char]) -> Vec<Token> {
+    let text: String = inner.iter().collect();
+    if let Some((from, to)) = text.split_once("..") {
+        if let (Ok(from), Ok(to)) = (from.parse(), to.parse()) {
+            return vec![Token::NumberRange(from, to)];
+        }
+    }
+    let mut alternatives = Vec::new();
+    let mut depth: usize = 0;
+    let mut start = 0;
+    let mut index = 0;
+    while let Some(&ch) = inner.get(index) {
+        match ch {
+            '\\' => index = index.saturating_add(1),
+            '{' => depth = depth.saturating_add(1),
+            '}' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                alternatives.push(parse_glob(inner.get(start..index).unwrap_or_default()));
+                start = index.saturating_add(1);
+            }
+            _ => (),
+        }
+        index = index.saturating_add(1);
+    }
+    if alternatives.is_empty() {
+        let mut tokens = vec![Token::Literal('{')];
+        tokens.extend(parse_glob(inner));
+        tokens.push(Token::Literal('}'));
+        return tokens;
+    }
+    alternatives.push(parse_glob(inner.get(start..).unwrap_or_default()));
+    vec![Token::Alternatives(alternatives)]
+}
+
+/// 判断文本是否与匹配单元完全匹配
+fn matches_tokens(tokens: &[Token], text: &[char]) -> bool {
+    let Some((token, rest)) = tokens.split_first() else {
+        return text.is_empty();
+    };
+    match token {
+        Token::Literal(ch) => text.first() == Some(ch) && matches_tokens(rest, text.get(1..).unwrap_or_default()),
+        Token::AnyChar => {
+            text.first().is_some_and(|ch| *ch != '/') && matches_tokens(rest, text.get(1..).unwrap_or_default())
+        }
+        Token::Star => (0..=text.len())
+            .take_while(|len| text.get(..*len).is_some_and(|prefix| !prefix.contains(&'/')))
+            .any(|len| matches_tokens(rest, text.get(len..).unwrap_or_default())),
+        Token::DoubleStar => {
+            // a/**/b 同时匹配a/b
+            let skip_slash = matches!(rest.first(), Some(Token::Literal('/')))
+                && matches_tokens(rest.get(1..).unwrap_or_default(), text);
+            skip_slash || (0..=text.len()).any(|len| matches_tokens(rest, text.get(len..).unwrap_or_default()))
+        }
+        Token::Class { negated, ranges } => text.first().is_some_and(|ch| {
+            *ch != '/' && ranges.iter().any(|(from, to)| (from..=to).contains(&ch)) != *negated
+        }) && matches_tokens(rest, text.get(1..).unwrap_or_default()),
+        Token::Alternatives(alternatives) => alternatives.iter().any(|alternative| {
+            let mut combined = alternative.clone();
+            combined.extend_from_slice(rest);
+            matches_tokens(&combined, text)
+        }),
+        Token::NumberRange(from, to) => {
+            let sign_len = usize::from(text.first() == Some(&'-'));
+            let digits = text.iter().skip(sign_len).take_while(|ch| ch.is_ascii_digit()).count();
+            if digits == 0 {
+                return false;
+            }
+            let end = sign_len.saturating_add(digits);
+            let number: String = text.get(..end).unwrap_or_default().iter().collect();
+            number.parse::<i64>().is_ok_and(|number| (*from.min(to)..=*from.max(to)).contains(&number))
+                && matches_tokens(rest, text.get(end..).unwrap_or_default())
+        }
+    }
+}
+
+/// 判断相对于.editorconfig所在目录的路径是否与section的glob匹配
+/// 不含/的glob匹配任意目录中的文件，含有/的glob相对于.editorconfig所在目录
+fn glob_matches(glob: &str, relative_path: &str) -> bool {
+    let glob = if glob.contains('/') {
+        glob.strip_prefix('/').unwrap_or(glob).to_string()
+    } else {
+        format!("**/{glob}")
+    };
+    let tokens = parse_glob(&glob.chars().collect::<Vec<_>>());
+    matches_tokens(&tokens, &relative_path.chars().collect::<Vec<_>>())
+}
+
+/// 一个.editorconfig文件的内容
+struct EditorConfigFile {
+    root: bool, // 是否为最顶层的配置，不再向上查找
+    sections: Vec<(String, Vec<(String, String)>)>, // 每个section的glob和其中的属性
+}
+
+impl EditorConfigFile {
+    /// 解析INI格式的内容，属性名称和值都转换为小写
+    fn parse(contents: &str) -> Self {
+        let mut root = false;
+        let mut sections: Vec<(String, Vec<(String, String)>)> = Vec::new();
+        for line in contents.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if let Some(glob) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                sections.push((glob.to_string(), Vec::new()));
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let key = key.trim().to_lowercase();
+            let value = value.trim().to_lowercase();
+            match sections.last_mut() {
+                Some((_, properties)) => properties.push((key, value)),
+                // 第一个section之前只有root属性有意义
+                None if key == "root" => root = value == "true",
+                None => (),
+            }
+        }
+        Self { root, sections }
+    }
+}
+
+/// 收集适用于文件的EditorConfig属性，离文件越近的.editorconfig优先级越高，同一文件中靠后的section优先
+fn collect_properties(file_name: &str) -> HashMap<String, String> {
+    let mut properties = HashMap::new();
+    let Ok(path) = path::absolute(file_name) else {
+        return properties;
+    };
+    let mut files = Vec::new();
+    for dir in path.ancestors().skip(1) {
+        let Ok(contents) = read_to_string(dir.join(EDITORCONFIG_FILE)) else {
+            continue;
+        };
+        let config = EditorConfigFile::parse(&contents);
+        let root = config.root;
+        files.push((dir, config));
+        if root {
+            break;
+        }
+    }
+    // 从最顶层的配置开始应用
+    for (dir, config) in files.iter().rev() {
+        let Some(relative_path) = relative_path(&path, dir) else {
+            continue;
+        };
+        for (glob, section) in &config.sections {
+            if glob_matches(glob, &relative_path) {
+                for (key, value) in section {
+                    properties.insert(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+    properties
+}
+
+/// 文件相对于目录的路径，使用/分隔
+fn relative_path(path: &Path, dir: &Path) -> Option<String> {
+    let relative = path.strip_prefix(dir).ok()?;
+    let components: Vec<String> = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().to_string())
+        .collect();
+    Some(components.join("/"))
+}
+
+/// 将适用于文件的EditorConfig属性应用到设置，不支持的属性值返回错误信息
+/// 属性值为unset时恢复为默认设置，不认识的属性和无效的值按照规范忽略
+pub fn apply(settings: &mut Settings, file_name: &str) -> Vec<String> {
+    let properties = collect_properties(file_name);
+    let defaults = Settings::default();
+    let mut errors = Vec::new();
+    let property = |key: &str| properties.get(key).map(String::as_str);
+    let parse_size = |value: &str| value.parse::<usize>().ok().filter(|size| *size > 0);
+
+    match property("indent_style") {
+        Some("tab") => settings.indent_style = IndentStyle::Tabs,
+        Some("space") => settings.indent_style = IndentStyle::Spaces,
+        Some("unset") => settings.indent_style = defaults.indent_style,
+        _ => (),
+    }
+    match property("tab_width") {
+        Some("unset") => settings.tab_width = defaults.tab_width,
+        Some(value) => settings.tab_width = parse_size(value).unwrap_or(settings.tab_width),
+        None => (),
+    }
+    match property("indent_size") {
+        // 使用制表符宽度作为缩进宽度
+        Some("tab") => settings.indent_size = settings.tab_width,
+        Some("unset") => settings.indent_size = defaults.indent_size,
+        Some(value) => {
+            if let Some(indent_size) = parse_size(value) {
+                settings.indent_size = indent_size;
+                // 没有指定tab_width时与indent_size相同
+                if property("tab_width").is_none() {
+                    settings.tab_width = indent_size;
+                }
+            }
+        }
+        None => (),
+    }
+    match property("end_of_line") {
+        Some("lf") => settings.line_ending = Some(LineEnding::Lf),
+        Some("crlf") => settings.line_ending = Some(LineEnding::Crlf),
+        Some("unset") => settings.line_ending = defaults.line_ending,
+        Some(value) => errors.push(format!("unsupported end_of_line '{value}'")),
+        None => (),
+    }
+    match property("charset") {
+        Some("utf-8") => settings.charset = Some(Charset::Utf8),
+        Some("utf-8-bom") => settings.charset = Some(Charset::Utf8Bom),
+        Some("unset") => settings.charset = defaults.charset,
+        Some(value) => errors.push(format!("unsupported charset '{value}'")),
+        None => (),
+    }
+    match property("trim_trailing_whitespace") {
+        Some("true") => settings.trim_trailing_whitespace = true,
+        Some("false") => settings.trim_trailing_whitespace = false,
+        Some("unset") => settings.trim_trailing_whitespace = defaults.trim_trailing_whitespace,
+        _ => (),
+    }
+    match property("insert_final_newline") {
+        Some("true") => settings.insert_final_newline = true,
+        Some("false") => settings.insert_final_newline = false,
+        Some("unset") => settings.insert_final_newline = defaults.insert_final_newline,
+        _ => (),
+    }
+    errors.into_iter().map(|err| format!("{EDITORCONFIG_FILE}: {err}")).collect()
+}
+
+#[test]
+fn test_glob_matches() {
+    assert!(glob_matches("*", "src/main.rs"));
+    assert!(glob_matches("*.rs", "src/main.rs"));
+    assert!(!glob_matches("/*.rs", "src/main.rs"));
+    assert!(glob_matches("src/*.rs", "src/main.rs"));
+    assert!(glob_matches("src/**/*.rs", "src/main.rs"));
+    assert!(glob_matches("src/**/*.rs", "src/editor/view/line.rs"));
+    assert!(glob_matches("*.{js,py}", "lib/a.py"));
+    assert!(!glob_matches("*.{js,py}", "lib/a.rs"));
+    assert!(glob_matches("{Makefile,*.mk}", "Makefile"));
+    assert!(glob_matches("file[0-9].txt", "file7.txt"));
+    assert!(!glob_matches("file[!0-9].txt", "file7.txt"));
+    assert!(glob_matches("test{1..12}.c", "test10.c"));
+    assert!(!glob_matches("test{1..12}.c", "test13.c"));
+    assert!(glob_matches("{single}", "{single}"));
+
+    let config = EditorConfigFile::parse("root = true\n\n[*]\nindent_style = Space\n; comment\n[*.md]\ntrim_trailing_whitespace = false\n");
+    assert!(config.root);
+    assert_eq!(config.sections.len(), 2);
+    assert_eq!(config.sections.first().map(|(_, section)| section.clone()), Some(vec![("indent_style".to_string(), "space".to_string())]));
+}
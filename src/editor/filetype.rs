@@ -0,0 +1,116 @@
+use std::{fmt, path::Path};
+
+/// 根据文件名识别的文件类型
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum FileType {
+    Rust,
+    Python,
+    Shell,
+    C,
+    Toml,
+    Yaml,
+    Markdown,
+    Makefile,
+    #[default]
+    Text,
+}
+
+impl FileType {
+    /// 根据文件名（扩展名或特殊文件名）识别文件类型
+    pub fn from_file_name(file_name: &str) -> Self {
+        let path = Path::new(file_name);
+        let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        if name == "Makefile" || name == "makefile" || name == "GNUmakefile" {
+            return Self::Makefile;
+        }
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "rs" => Self::Rust,
+            "py" | "pyw" => Self::Python,
+            "sh" | "bash" | "zsh" => Self::Shell,
+            "c" | "h" | "cpp" | "hpp" | "cc" => Self::C,
+            "toml" => Self::Toml,
+            "yml" | "yaml" => Self::Yaml,
+            "md" | "markdown" => Self::Markdown,
+            "mk" => Self::Makefile,
+            _ => Self::Text,
+        }
+    }
+
+    /// 行注释的开头，没有行注释的文件类型返回None
+    pub const fn line_comment(self) -> Option<&'static str> {
+        match self {
+            Self::Rust | Self::C => Some("//"),
+            Self::Python | Self::Shell | Self::Toml | Self::Yaml | Self::Makefile => Some("#"),
+            Self::Markdown | Self::Text => None,
+        }
+    }
+
+    /// 是否必须使用制表符缩进，例如Makefile中的命令行
+    pub const fn requires_tabs(self) -> bool {
+        matches!(self, Self::Makefile)
+    }
+
+    /// 以text结尾的行之后的新行是否需要增加一级缩进
+    /// 括号之后总是增加缩进，Python和YAML中冒号之后也增加缩进
+    pub fn indents_after(self, text: &str) -> bool {
+        let Some(last) = text.trim_end().chars().last() else {
+            return false;
+        };
+        match self {
+            Self::Python | Self::Yaml => matches!(last, '{' | '[' | '(' | ':'),
+            Self::Rust | Self::Shell | Self::C | Self::Toml | Self::Makefile => matches!(last, '{' | '[' | '('),
+            Self::Markdown | Self::Text => false,
+        }
+    }
+
+    /// 在行首输入该字符时是否减少一级缩进
+    pub const fn dedents_on(self, ch: char) -> bool {
+        match self {
+            Self::Rust | Self::Python | Self::Shell | Self::C | Self::Toml | Self::Yaml | Self::Makefile => {
+                matches!(ch, '}' | ']' | ')')
+            }
+            Self::Markdown | Self::Text => false,
+        }
+    }
+
+    /// 输入时自动闭合的字符对，Rust中的单引号也用于生命周期，不自动闭合
+    pub const fn auto_close_pairs(self) -> &'static [(char, char)] {
+        match self {
+            Self::Rust | Self::Makefile => &[('(', ')'), ('[', ']'), ('{', '}'), ('"', '"')],
+            Self::Python | Self::Shell | Self::C | Self::Toml | Self::Yaml => {
+                &[('(', ')'), ('[', ']'), ('{', '}'), ('"', '"'), ('\'', '\'')]
+            }
+            Self::Markdown | Self::Text => &[('(', ')'), ('[', ']'), ('{', '}')],
+        }
+    }
+
+    /// 字符串的引号，Rust中的单引号也用于生命周期，不作为引号处理
+    pub const fn string_quotes(self) -> &'static [char] {
+        match self {
+            Self::Rust => &['"'],
+            Self::Python | Self::Shell | Self::C | Self::Toml | Self::Yaml => &['"', '\''],
+            Self::Markdown | Self::Makefile | Self::Text => &[],
+        }
+    }
+}
+
+impl fmt::Display for FileType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Rust => "Rust",
+            Self::Python => "Python",
+            Self::Shell => "Shell",
+            Self::C => "C",
+            Self::Toml => "TOML",
+            Self::Yaml => "YAML",
+            Self::Markdown => "Markdown",
+            Self::Makefile => "Makefile",
+            Self::Text => "Text",
+        };
+        write!(f, "{name}")
+    }
+}
@@ -0,0 +1,479 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    fs::read_to_string,
+    io::ErrorKind,
+};
+
+use super::{config::config_dir, editorcommand::EditorCommand};
+
+/// 按键绑定配置文件名
+const KEYMAP_FILE: &str = "keymap.toml";
+
+/// 取消绑定时使用的命令名称
+const UNBIND: &str = "none";
+
+/// 默认按键绑定，与配置文件使用相同的格式
+const DEFAULT_BINDINGS: &[(&str, &str)] = &[
+    ("ctrl-c", "quit"),
+    ("ctrl-s", "save"),
+    ("alt-a", "save_as"),
+    ("ctrl-o", "open"),
+    ("ctrl-l", "list_buffers"),
+    ("ctrl-w", "close_buffer"),
+    ("ctrl-pagedown", "next_buffer"),
+    ("ctrl-pageup", "prev_buffer"),
+    ("ctrl-p", "command_line"),
+    ("ctrl-f", "search"),
+    ("f3", "search_next"),
+    ("shift-f3", "search_prev"),
+    ("ctrl-g", "goto_line"),
+    ("alt-s", "split_horizontal"),
+    ("alt-v", "split_vertical"),
+    ("alt-q", "close_pane"),
+    ("alt-o", "focus_next_pane"),
+    ("alt-t", "toggle_tab_bar"),
+    ("alt-=", "grow_pane"),
+    ("alt-+", "grow_pane"),
+    ("alt--", "shrink_pane"),
+    ("alt-up", "focus_up"),
+    ("alt-down", "focus_down"),
+    ("alt-left", "focus_left"),
+    ("alt-right", "focus_right"),
+    ("up", "move_up"),
+    ("down", "move_down"),
+    ("left", "move_left"),
+    ("right", "move_right"),
+    ("home", "move_home"),
+    ("end", "move_end"),
+    ("pageup", "page_up"),
+    ("pagedown", "page_down"),
+    ("backspace", "backspace"),
+    ("delete", "delete"),
+    ("enter", "insert_newline"),
+    ("tab", "indent"),
+    ("shift-tab", "outdent"),
+    ("alt-i", "insert_tab"), // 不论缩进设置如何都插入制表符
+    ("alt-;", "toggle_comment"),
+    ("ctrl-z", "undo"),
+    ("ctrl-y", "redo"),
+    ("ctrl-left", "word_left"),
+    ("ctrl-right", "word_right"),
+    ("ctrl-backspace", "delete_word_backward"),
+    ("alt-backspace", "delete_word_backward"), // 不能区分Ctrl+Backspace的终端中使用
+    ("ctrl-delete", "delete_word_forward"),
+    ("ctrl-up", "prev_paragraph"),
+    ("ctrl-down", "next_paragraph"),
+    ("ctrl-home", "document_start"),
+    ("ctrl-end", "document_end"),
+    ("alt-]", "matching_bracket"),
+    ("ctrl-alt-up", "add_caret_above"),
+    ("ctrl-alt-down", "add_caret_below"),
+    ("ctrl-d", "add_next_occurrence"),
+    ("esc", "clear_selection"),
+    ("ctrl-b", "select_block"),
+    ("alt-.", "toggle_whitespace"),
+];
+
+/// Emacs按键绑定，在默认按键绑定的基础上覆盖
+const EMACS_BINDINGS: &[(&str, &str)] = &[
+    ("ctrl-a", "move_home"),
+    ("ctrl-e", "move_end"),
+    ("ctrl-f", "move_right"),
+    ("ctrl-b", "move_left"),
+    ("ctrl-n", "move_down"),
+    ("ctrl-p", "move_up"),
+    ("alt-f", "word_right"),
+    ("alt-b", "word_left"),
+    ("alt-d", "delete_word_forward"),
+    ("alt-backspace", "delete_word_backward"),
+    ("alt-{", "prev_paragraph"),
+    ("alt-}", "next_paragraph"),
+    ("alt-<", "document_start"),
+    ("alt->", "document_end"),
+    ("ctrl-v", "page_down"),
+    ("alt-v", "page_up"),
+    ("ctrl-d", "delete"),
+    ("ctrl-k", "kill_line"),
+    ("ctrl-y", "yank"),
+    ("alt-y", "yank_pop"),
+    ("ctrl-space", "select"),
+    ("ctrl-x space", "select_block"),
+    ("ctrl-w", "cut"),
+    ("alt-w", "copy"),
+    ("ctrl-g", "clear_selection"),
+    ("ctrl-s", "search"),
+    ("alt-g", "goto_line"),
+    ("alt-x", "command_line"),
+    ("ctrl-x ctrl-s", "save"),
+    ("ctrl-x ctrl-w", "save_as"),
+    ("ctrl-x ctrl-f", "open"),
+    ("ctrl-x ctrl-c", "quit"),
+    ("ctrl-x b", "list_buffers"),
+    ("ctrl-x k", "close_buffer"),
+    ("ctrl-x 2", "split_horizontal"),
+    ("ctrl-x 3", "split_vertical"),
+    ("ctrl-x 0", "close_pane"),
+    ("ctrl-x o", "focus_next_pane"),
+    ("ctrl-x u", "undo"),
+    ("ctrl-q tab", "insert_tab"),
+];
+
+/// vi按键绑定，在默认按键绑定的基础上覆盖
+const VI_BINDINGS: &[(&str, &str)] = &[("ctrl-r", "redo")];
+
+/// 按键绑定方案
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum Profile {
+    #[default]
+    Default, // 默认按键绑定
+    Vi, // 在默认按键绑定之上增加vi模式编辑层
+    Emacs, // Emacs风格的按键绑定
+}
+
+impl TryFrom<&str> for Profile {
+    type Error = String;
+
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        match name {
+            "default" => Ok(Self::Default),
+            "vi" => Ok(Self::Vi),
+            "emacs" => Ok(Self::Emacs),
+            _ => Err(format!("unknown profile '{name}'")),
+        }
+    }
+}
+
+/// 一次按键，由按键和修饰键组成
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct KeyChord {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl From<KeyEvent> for KeyChord {
+    /// 字符按键的大小写已经体现在字符中，Shift-Tab即为BackTab，忽略Shift修饰键
+    fn from(event: KeyEvent) -> Self {
+        let modifiers = match event.code {
+            KeyCode::Char(_) | KeyCode::BackTab => event.modifiers.difference(KeyModifiers::SHIFT),
+            _ => event.modifiers,
+        };
+        Self { code: event.code, modifiers }
+    }
+}
+
+impl TryFrom<&str> for KeyChord {
+    type Error = String;
+
+    /// 解析形如ctrl-s、alt-left、ctrl-alt-x、f5的按键描述
+    fn try_from(description: &str) -> Result<Self, Self::Error> {
+        // 最后一个-之后为按键名称，alt--表示Alt和减号
+        let (modifier_part, key_name) = if description == "-" {
+            ("", "-")
+        } else if let Some(modifier_part) = description.strip_suffix("--") {
+            (modifier_part, "-")
+        } else {
+            description.rsplit_once('-').unwrap_or(("", description))
+        };
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in modifier_part.split('-').filter(|modifier| !modifier.is_empty()) {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "c" => KeyModifiers::CONTROL,
+                "alt" | "meta" | "m" => KeyModifiers::ALT,
+                "shift" | "s" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier '{modifier}'")),
+            };
+        }
+        let code = match key_name.to_lowercase().as_str() {
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" => KeyCode::Insert,
+            "enter" | "return" => KeyCode::Enter,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "esc" | "escape" => KeyCode::Esc,
+            "space" => KeyCode::Char(' '),
+            name => {
+                let mut chars = key_name.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(ch), None) => KeyCode::Char(ch),
+                    _ => match name.strip_prefix('f').and_then(|number| number.parse::<u8>().ok()) {
+                        Some(number) if (1..=12).contains(&number) => KeyCode::F(number),
+                        _ => return Err(format!("unknown key '{key_name}'")),
+                    },
+                }
+            }
+        };
+        let mut chord = Self { code, modifiers };
+        // 与按键事件保持一致，字符按键和BackTab忽略Shift
+        if modifiers.contains(KeyModifiers::SHIFT) {
+            match code {
+                KeyCode::Char(ch) => chord.code = KeyCode::Char(ch.to_ascii_uppercase()),
+                KeyCode::Tab => chord.code = KeyCode::BackTab,
+                _ => (),
+            }
+            if matches!(chord.code, KeyCode::Char(_) | KeyCode::BackTab) {
+                chord.modifiers.remove(KeyModifiers::SHIFT);
+            }
+        }
+        Ok(chord)
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "Shift-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(ch) => write!(f, "{}", ch.to_ascii_uppercase()),
+            code => write!(f, "{code:?}"),
+        }
+    }
+}
+
+/// 解析由空格分隔的按键序列，例如"ctrl-k ctrl-s"
+fn parse_sequence(description: &str) -> Result<Vec<KeyChord>, String> {
+    let sequence = description
+        .split_whitespace()
+        .map(KeyChord::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+    if sequence.is_empty() {
+        return Err("empty key sequence".to_string());
+    }
+    Ok(sequence)
+}
+
+/// 查找按键绑定的结果
+pub enum KeymapResult {
+    Command(EditorCommand), // 绑定的命令
+    Pending(String), // 按键序列尚未完成，内容为已经输入的按键
+    Unbound, // 没有绑定的单个按键，忽略
+    UnboundSequence(String), // 没有绑定的按键序列，内容为输入的按键
+}
+
+/// 按键绑定表，将按键或按键序列映射到命令
+pub struct Keymap {
+    bindings: HashMap<Vec<KeyChord>, EditorCommand>,
+    prefixes: HashSet<Vec<KeyChord>>, // 所有按键序列的前缀，用于判断是否需要等待后续按键
+    pending: Vec<KeyChord>, // 已经输入的未完成序列
+    profile: Profile, // 按键绑定方案
+}
+
+impl Keymap {
+    /// 加载按键绑定，先应用默认绑定，再应用用户配置目录中的配置文件
+    /// 返回按键绑定表以及配置文件中的错误信息
+    pub fn load() -> (Self, Vec<String>) {
+        let mut keymap = Self::default();
+        let mut errors = Vec::new();
+        if let Some(path) = config_dir().map(|dir| dir.join(KEYMAP_FILE)) {
+            match read_to_string(&path) {
+                Ok(contents) => errors = keymap.apply_config(&contents),
+                Err(err) if err.kind() == ErrorKind::NotFound => (),
+                Err(err) => errors.push(format!("Could not read {}: {err}", path.display())),
+            }
+            for error in &mut errors {
+                *error = format!("{KEYMAP_FILE}: {error}");
+            }
+        }
+        (keymap, errors)
+    }
+
+    /// 应用配置文件内容，profile选择按键绑定方案，[bindings]表中每一项为按键序列到命令名称的映射
+    /// 命令名称为none时取消该按键序列的绑定；有错误的项会被跳过并返回错误信息
+    pub fn apply_config(&mut self, contents: &str) -> Vec<String> {
+        let table = match contents.parse::<toml::Table>() {
+            Ok(table) => table,
+            Err(err) => return vec![format!("invalid TOML: {}", err.message())],
+        };
+        let mut errors = Vec::new();
+        for key in table.keys() {
+            if key != "bindings" && key != "profile" {
+                errors.push(format!("unknown section '{key}'"));
+            }
+        }
+        if let Some(profile) = table.get("profile") {
+            match profile.as_str().ok_or_else(|| "'profile' must be a string".to_string()).and_then(Profile::try_from) {
+                Ok(profile) => *self = Self::with_profile(profile),
+                Err(err) => errors.push(err),
+            }
+        }
+        let Some(bindings) = table.get("bindings") else {
+            return errors;
+        };
+        let Some(bindings) = bindings.as_table() else {
+            errors.push("'bindings' must be a table".to_string());
+            return errors;
+        };
+        for (keys, command) in bindings {
+            let sequence = match parse_sequence(keys) {
+                Ok(sequence) => sequence,
+                Err(err) => {
+                    errors.push(format!("invalid key sequence '{keys}': {err}"));
+                    continue;
+                }
+            };
+            let Some(command) = command.as_str() else {
+                errors.push(format!("command for '{keys}' must be a string"));
+                continue;
+            };
+            if command == UNBIND {
+                self.bindings.remove(&sequence);
+                continue;
+            }
+            match EditorCommand::try_from(command) {
+                Ok(command) => {
+                    self.bindings.insert(sequence, command);
+                }
+                Err(err) => errors.push(format!("invalid binding for '{keys}': {err}")),
+            }
+        }
+        self.rebuild_prefixes();
+        // 一个按键序列是另一个序列的前缀时，较短的序列永远不会被触发
+        for sequence in self.bindings.keys() {
+            if self.prefixes.contains(sequence) {
+                errors.push(format!("'{}' is shadowed by a longer key sequence", display_sequence(sequence)));
+            }
+        }
+        errors
+    }
+
+    /// 重新计算所有按键序列的前缀
+    fn rebuild_prefixes(&mut self) {
+        self.prefixes = self
+            .bindings
+            .keys()
+            .flat_map(|sequence| (1..sequence.len()).map(|len| sequence.iter().take(len).copied().collect()))
+            .collect();
+    }
+
+    /// 根据按键查找命令，按键序列未完成时等待后续按键
+    /// 没有绑定的普通字符按键插入该字符
+    pub fn lookup(&mut self, event: KeyEvent) -> KeymapResult {
+        let chord = KeyChord::from(event);
+        self.pending.push(chord);
+        if self.prefixes.contains(&self.pending) {
+            return KeymapResult::Pending(display_sequence(&self.pending));
+        }
+        let sequence = std::mem::take(&mut self.pending);
+        if let Some(command) = self.bindings.get(&sequence) {
+            return KeymapResult::Command(*command);
+        }
+        match (sequence.as_slice(), chord.code, chord.modifiers) {
+            ([_], KeyCode::Char(ch), KeyModifiers::NONE) => KeymapResult::Command(EditorCommand::Insert(ch)),
+            ([_], _, _) => KeymapResult::Unbound,
+            _ => KeymapResult::UnboundSequence(display_sequence(&sequence)),
+        }
+    }
+}
+
+impl Keymap {
+    /// 按键绑定方案对应的默认按键绑定
+    fn with_profile(profile: Profile) -> Self {
+        let mut keymap = Self {
+            bindings: HashMap::new(),
+            prefixes: HashSet::new(),
+            pending: Vec::new(),
+            profile,
+        };
+        let overrides = match profile {
+            Profile::Emacs => EMACS_BINDINGS,
+            Profile::Vi => VI_BINDINGS,
+            Profile::Default => &[],
+        };
+        for (keys, command) in DEFAULT_BINDINGS.iter().chain(overrides) {
+            if let (Ok(sequence), Ok(command)) = (parse_sequence(keys), EditorCommand::try_from(*command)) {
+                keymap.bindings.insert(sequence, command);
+            }
+        }
+        keymap.rebuild_prefixes();
+        keymap
+    }
+
+    /// 绑定到命令的按键序列的可读文本，有多个时选择最短的，没有绑定时返回None
+    pub fn keys_for(&self, command: EditorCommand) -> Option<String> {
+        self.bindings
+            .iter()
+            .filter(|(_, bound)| **bound == command)
+            .map(|(sequence, _)| (sequence.len(), display_sequence(sequence)))
+            .min()
+            .map(|(_, keys)| keys)
+    }
+
+    /// 单个按键直接绑定的命令，不影响等待中的按键序列，用于输入框中处理部分命令
+    pub fn binding(&self, event: KeyEvent) -> Option<EditorCommand> {
+        self.bindings.get(&vec![KeyChord::from(event)]).copied()
+    }
+
+    /// 按键绑定方案
+    pub const fn profile(&self) -> Profile {
+        self.profile
+    }
+}
+
+impl Default for Keymap {
+    /// 默认按键绑定
+    fn default() -> Self {
+        Self::with_profile(Profile::default())
+    }
+}
+
+/// 将按键序列转换为可读的文本
+fn display_sequence(sequence: &[KeyChord]) -> String {
+    sequence.iter().map(ToString::to_string).collect::<Vec<_>>().join(" ")
+}
+
+#[test]
+fn test_default_bindings_are_valid() {
+    for (keys, command) in DEFAULT_BINDINGS.iter().chain(EMACS_BINDINGS).chain(VI_BINDINGS) {
+        assert!(parse_sequence(keys).is_ok(), "invalid default key: {keys}");
+        assert!(EditorCommand::try_from(*command).is_ok(), "invalid default command: {command}");
+    }
+}
+
+#[test]
+fn test_apply_config() {
+    let mut keymap = Keymap::default();
+    let errors = keymap.apply_config(
+        r#"
+        [bindings]
+        "ctrl-c" = "none"
+        "ctrl-q" = "quit"
+        "ctrl-k ctrl-s" = "save"
+        "ctrl-?x" = "save"
+        "ctrl-j" = "frobnicate"
+        "#,
+    );
+    assert_eq!(errors.len(), 2);
+    let ctrl = |ch| KeyEvent::new(KeyCode::Char(ch), KeyModifiers::CONTROL);
+    assert!(matches!(keymap.lookup(ctrl('c')), KeymapResult::Unbound));
+    assert!(matches!(keymap.lookup(ctrl('q')), KeymapResult::Command(EditorCommand::Quit)));
+    assert!(matches!(keymap.lookup(ctrl('k')), KeymapResult::Pending(_)));
+    assert!(matches!(keymap.lookup(ctrl('s')), KeymapResult::Command(EditorCommand::Save)));
+    let shift_a = KeyEvent::new(KeyCode::Char('A'), KeyModifiers::SHIFT);
+    assert!(matches!(keymap.lookup(shift_a), KeymapResult::Command(EditorCommand::Insert('A'))));
+    assert!(keymap.apply_config("profile = \"vi\"").is_empty());
+    assert_eq!(keymap.profile(), Profile::Vi);
+    assert!(matches!(keymap.lookup(ctrl('c')), KeymapResult::Command(_)));
+    assert_eq!(keymap.apply_config("profile = \"nano\"").len(), 1);
+    assert!(keymap.apply_config("profile = \"emacs\"").is_empty());
+    assert_eq!(keymap.keys_for(EditorCommand::CloseBuffer).as_deref(), Some("Ctrl-X K"));
+    assert!(matches!(keymap.lookup(ctrl('x')), KeymapResult::Pending(_)));
+    assert!(matches!(keymap.lookup(ctrl('s')), KeymapResult::Command(EditorCommand::Save)));
+}
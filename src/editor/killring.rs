@@ -0,0 +1,73 @@
+use std::collections::VecDeque;
+
+/// kill ring中最多保存的条目数
+const KILL_RING_SIZE: usize = 32;
+
+/// 一次复制或剪切的文本
+pub struct Kill {
+    pub text: String, // 文本内容
+    pub linewise: bool, // 是否按行复制，按行复制的文本粘贴为独立的行
+}
+
+/// 保存最近复制或剪切的文本，最新的条目在最前面
+#[derive(Default)]
+pub struct KillRing {
+    kills: VecDeque<Kill>,
+    index: usize, // 当前条目的下标，粘贴时使用，循环选择之前的条目时递增
+}
+
+impl KillRing {
+    /// 添加新的条目并将其设为当前条目，超出容量时丢弃最旧的条目
+    pub fn push(&mut self, text: String, linewise: bool) {
+        if text.is_empty() {
+            return;
+        }
+        self.kills.push_front(Kill { text, linewise });
+        self.kills.truncate(KILL_RING_SIZE);
+        self.index = 0;
+    }
+
+    /// 将文本追加到最新的条目，用于连续剪切；类型不同或没有条目时添加新的条目
+    pub fn append(&mut self, text: String, linewise: bool) {
+        match self.kills.front_mut() {
+            Some(kill) if kill.linewise == linewise => {
+                kill.text.push_str(&text);
+                self.index = 0;
+            }
+            _ => self.push(text, linewise),
+        }
+    }
+
+    /// 当前条目
+    pub fn current(&self) -> Option<&Kill> {
+        self.kills.get(self.index)
+    }
+
+    /// 将当前条目切换为上一个更早的条目，到达最旧的条目后回到最新的条目
+    pub fn rotate(&mut self) -> Option<&Kill> {
+        self.index = self.index.saturating_add(1).checked_rem(self.kills.len()).unwrap_or(0);
+        self.current()
+    }
+
+    /// 将当前条目恢复为最新的条目，循环选择结束后调用
+    pub fn reset_rotation(&mut self) {
+        self.index = 0;
+    }
+}
+
+#[test]
+fn test_kill_ring() {
+    let mut ring = KillRing::default();
+    assert!(ring.current().is_none());
+    ring.push("one".to_string(), false);
+    ring.append(" two".to_string(), false);
+    ring.push("three\n".to_string(), true);
+    ring.append("four".to_string(), false);
+    assert_eq!(ring.current().map(|kill| kill.text.as_str()), Some("four"));
+    assert_eq!(ring.rotate().map(|kill| kill.text.as_str()), Some("three\n"));
+    assert_eq!(ring.rotate().map(|kill| kill.text.as_str()), Some("one two"));
+    assert_eq!(ring.rotate().map(|kill| kill.text.as_str()), Some("four"));
+    ring.rotate();
+    ring.reset_rotation();
+    assert_eq!(ring.current().map(|kill| kill.text.as_str()), Some("four"));
+}
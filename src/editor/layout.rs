@@ -0,0 +1,420 @@
+use std::mem;
+use unicode_width::UnicodeWidthChar;
+
+use super::{
+    editorcommand::{PaneDirection, SplitDirection},
+    terminal::{Position, Size, Terminal},
+    view::View,
+};
+
+/// 窗格占总空间的最小/最大百分比
+const MIN_RATIO: usize = 10;
+const MAX_RATIO: usize = 90;
+
+/// 一个窗格，显示某个buffer的一个view
+/// 每个窗格有自己的光标和滚动偏移，多个窗格可以共享同一个buffer
+pub struct Pane {
+    pub id: usize, // 窗格唯一标识
+    pub buffer_index: usize, // 显示的buffer在Editor buffer列表中的下标
+    pub view: View, // 窗格中的view
+}
+
+impl Pane {
+    /// 在窗格底部渲染状态行，显示编辑模式、文件名、修改标记和光标位置，拥有焦点的窗格反色显示
+    pub fn render_status(&self, at: Position, width: usize, focused: bool, mode: Option<&str>) {
+        if width == 0 {
+            return;
+        }
+        let name = self.view.file_name().unwrap_or_else(|| "[No Name]".to_string());
+        let modified = if self.view.is_modified() { " [+]" } else { "" };
+        let location = self.view.text_location();
+        let mode = mode.map_or_else(String::new, |mode| format!(" {mode} |"));
+        let left = format!("{mode} {name}{modified}");
+        let right = format!(
+            "{} | {}:{} ",
+            self.view.file_type(),
+            location.line_index.saturating_add(1),
+            location.grapheme_index.saturating_add(1)
+        );
+        let mut status = String::new();
+        let mut status_width: usize = 0;
+        // 左侧内容超出宽度时截断，右侧内容放不下时省略
+        for ch in left.chars() {
+            let ch_width = ch.width().unwrap_or(0);
+            if status_width.saturating_add(ch_width) > width {
+                break;
+            }
+            status.push(ch);
+            status_width = status_width.saturating_add(ch_width);
+        }
+        // 没有焦点的窗格用-填充，与拥有焦点的窗格区分
+        let fill = if focused { " " } else { "-" };
+        let remaining = width.saturating_sub(status_width);
+        if remaining > right.len() {
+            status.push_str(&fill.repeat(remaining.saturating_sub(right.len())));
+            status.push_str(&right);
+        } else {
+            status.push_str(&fill.repeat(remaining));
+        }
+        let result = if focused {
+            Terminal::print_inverted_at(at, &status)
+        } else {
+            Terminal::print_in_area(at, width, &status)
+        };
+        debug_assert!(result.is_ok(), "Failed to render pane status");
+    }
+}
+
+/// 布局树的节点，叶子节点对应窗格，分支节点将空间一分为二
+enum Node {
+    Leaf(usize),
+    Split {
+        direction: SplitDirection,
+        ratio: usize, // 第一个子节点所占的百分比
+        first: Box<Node>,
+        second: Box<Node>,
+    },
+}
+
+/// 窗格在terminal中占据的区域
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Area {
+    pub origin: Position,
+    pub size: Size,
+}
+
+impl Area {
+    /// 区域右边界（不包含）
+    const fn right(&self) -> usize {
+        self.origin.col.saturating_add(self.size.width)
+    }
+
+    /// 区域下边界（不包含）
+    const fn bottom(&self) -> usize {
+        self.origin.row.saturating_add(self.size.height)
+    }
+}
+
+/// 窗格布局，由一棵二叉树描述每个窗格的位置和大小
+pub struct Layout {
+    root: Node,
+    panes: Vec<Pane>,
+    focused: usize, // 当前拥有焦点的窗格id
+    next_id: usize, // 下一个新窗格的id
+}
+
+impl Layout {
+    /// 创建只包含一个窗格的布局
+    pub fn new(view: View, buffer_index: usize) -> Self {
+        Self {
+            root: Node::Leaf(0),
+            panes: vec![Pane { id: 0, buffer_index, view }],
+            focused: 0,
+            next_id: 1,
+        }
+    }
+
+    /// 当前拥有焦点的窗格
+    pub fn focused_pane(&self) -> Option<&Pane> {
+        self.panes.iter().find(|pane| pane.id == self.focused)
+    }
+
+    /// 当前拥有焦点的窗格（可变）
+    pub fn focused_pane_mut(&mut self) -> Option<&mut Pane> {
+        self.panes.iter_mut().find(|pane| pane.id == self.focused)
+    }
+
+    /// 判断窗格是否拥有焦点
+    pub const fn is_focused(&self, pane: &Pane) -> bool {
+        pane.id == self.focused
+    }
+
+    /// 所有窗格
+    pub fn panes(&self) -> &[Pane] {
+        &self.panes
+    }
+
+    /// 所有窗格（可变）
+    pub fn panes_mut(&mut self) -> &mut [Pane] {
+        &mut self.panes
+    }
+
+    /// 分割当前窗格，新窗格与当前窗格共享同一个buffer，并获得焦点
+    pub fn split(&mut self, direction: SplitDirection) {
+        let Some(pane) = self.focused_pane() else {
+            return;
+        };
+        let new_pane = Pane {
+            id: self.next_id,
+            buffer_index: pane.buffer_index,
+            view: pane.view.clone(),
+        };
+        let new_id = new_pane.id;
+        self.next_id = self.next_id.saturating_add(1);
+        if let Some(node) = Self::find_leaf(&mut self.root, self.focused) {
+            *node = Node::Split {
+                direction,
+                ratio: 50,
+                first: Box::new(Node::Leaf(self.focused)),
+                second: Box::new(Node::Leaf(new_id)),
+            };
+        }
+        self.panes.push(new_pane);
+        self.focused = new_id;
+    }
+
+    /// 关闭当前窗格，兄弟节点占据其空间；只剩一个窗格时不能关闭，返回false
+    pub fn close_focused(&mut self) -> bool {
+        if self.panes.len() <= 1 {
+            return false;
+        }
+        let closed = self.focused;
+        let Some(next_focus) = Self::remove_leaf(&mut self.root, closed) else {
+            return false;
+        };
+        self.panes.retain(|pane| pane.id != closed);
+        // 焦点移动到占据其空间的兄弟节点中
+        self.focused = next_focus;
+        true
+    }
+
+    /// 只保留当前窗格，关闭其他所有窗格
+    pub fn only(&mut self) {
+        self.root = Node::Leaf(self.focused);
+        let focused = self.focused;
+        self.panes.retain(|pane| pane.id == focused);
+    }
+
+    /// 将焦点依次移动到下一个窗格
+    pub fn focus_next(&mut self) {
+        let mut order = Vec::new();
+        Self::collect_leaves(&self.root, &mut order);
+        if let Some(position) = order.iter().position(|id| *id == self.focused) {
+            let next = position.saturating_add(1).checked_rem(order.len()).unwrap_or(0);
+            if let Some(id) = order.get(next) {
+                self.focused = *id;
+            }
+        }
+    }
+
+    /// 将焦点移动到指定方向上相邻的窗格，没有相邻窗格时焦点不变
+    pub fn focus_direction(&mut self, direction: PaneDirection, area: Area) {
+        let areas = self.areas(area);
+        let Some(current) = areas.iter().find(|(id, _)| *id == self.focused).map(|(_, area)| *area) else {
+            return;
+        };
+        let candidate = areas
+            .iter()
+            .filter(|(id, _)| *id != self.focused)
+            .filter_map(|(id, other)| {
+                // 相邻窗格需要在移动方向上位于当前窗格之后，并且在另一个方向上有重叠
+                let overlap_rows = other.origin.row < current.bottom() && current.origin.row < other.bottom();
+                let overlap_cols = other.origin.col < current.right() && current.origin.col < other.right();
+                let distance = match direction {
+                    PaneDirection::Up if overlap_cols && other.bottom() <= current.origin.row => {
+                        current.origin.row.saturating_sub(other.bottom())
+                    }
+                    PaneDirection::Down if overlap_cols && other.origin.row >= current.bottom() => {
+                        other.origin.row.saturating_sub(current.bottom())
+                    }
+                    PaneDirection::Left if overlap_rows && other.right() <= current.origin.col => {
+                        current.origin.col.saturating_sub(other.right())
+                    }
+                    PaneDirection::Right if overlap_rows && other.origin.col >= current.right() => {
+                        other.origin.col.saturating_sub(current.right())
+                    }
+                    _ => return None,
+                };
+                Some((distance, *id))
+            })
+            .min();
+        if let Some((_, id)) = candidate {
+            self.focused = id;
+        }
+    }
+
+    /// 将焦点移动到包含指定位置的窗格，位置不在任何窗格中时返回false
+    pub fn focus_at(&mut self, position: Position, area: Area) -> bool {
+        let found = self.areas(area).into_iter().find(|(_, pane_area)| {
+            (pane_area.origin.row..pane_area.bottom()).contains(&position.row)
+                && (pane_area.origin.col..pane_area.right()).contains(&position.col)
+        });
+        if let Some((id, _)) = found {
+            self.focused = id;
+            return true;
+        }
+        false
+    }
+
+    /// 调整当前窗格所在分割的比例，delta为正时当前窗格变大
+    pub fn resize_focused(&mut self, delta: isize) {
+        Self::resize_parent(&mut self.root, self.focused, delta);
+    }
+
+    /// 计算每个窗格在给定区域中占据的位置
+    /// 左右排列的窗格之间留出一列作为分割线
+    pub fn areas(&self, area: Area) -> Vec<(usize, Area)> {
+        let mut result = Vec::new();
+        Self::collect_areas(&self.root, area, &mut result);
+        result
+    }
+
+    /// 计算左右排列的窗格之间分割线的位置，每一项为分割线起点和高度
+    pub fn separators(&self, area: Area) -> Vec<(Position, usize)> {
+        let mut result = Vec::new();
+        Self::collect_separators(&self.root, area, &mut result);
+        result
+    }
+
+    // region: tree helpers
+
+    /// 查找指定id的叶子节点
+    fn find_leaf(node: &mut Node, id: usize) -> Option<&mut Node> {
+        match node {
+            Node::Leaf(leaf) if *leaf == id => Some(node),
+            Node::Leaf(_) => None,
+            Node::Split { first, second, .. } => {
+                Self::find_leaf(first, id).or_else(|| Self::find_leaf(second, id))
+            }
+        }
+    }
+
+    /// 删除指定id的叶子节点，用其兄弟节点替换父节点，返回兄弟节点中的第一个窗格
+    fn remove_leaf(node: &mut Node, id: usize) -> Option<usize> {
+        let Node::Split { first, second, .. } = node else {
+            return None;
+        };
+        let sibling = if matches!(**first, Node::Leaf(leaf) if leaf == id) {
+            mem::replace(&mut **second, Node::Leaf(id))
+        } else if matches!(**second, Node::Leaf(leaf) if leaf == id) {
+            mem::replace(&mut **first, Node::Leaf(id))
+        } else {
+            return Self::remove_leaf(first, id).or_else(|| Self::remove_leaf(second, id));
+        };
+        *node = sibling;
+        Some(Self::first_leaf(node))
+    }
+
+    /// 按从左到右、从上到下的顺序收集叶子节点
+    fn collect_leaves(node: &Node, result: &mut Vec<usize>) {
+        match node {
+            Node::Leaf(id) => result.push(*id),
+            Node::Split { first, second, .. } => {
+                Self::collect_leaves(first, result);
+                Self::collect_leaves(second, result);
+            }
+        }
+    }
+
+    /// 第一个叶子节点
+    fn first_leaf(node: &Node) -> usize {
+        match node {
+            Node::Leaf(id) => *id,
+            Node::Split { first, .. } => Self::first_leaf(first),
+        }
+    }
+
+    /// 调整包含指定叶子节点的最内层分割的比例
+    fn resize_parent(node: &mut Node, id: usize, delta: isize) -> bool {
+        let Node::Split { ratio, first, second, .. } = node else {
+            return false;
+        };
+        if Self::resize_parent(first, id, delta) || Self::resize_parent(second, id, delta) {
+            return true;
+        }
+        let in_first = matches!(**first, Node::Leaf(leaf) if leaf == id);
+        let in_second = matches!(**second, Node::Leaf(leaf) if leaf == id);
+        if !in_first && !in_second {
+            return false;
+        }
+        // 当前窗格在第二个子节点中时，变大意味着第一个子节点变小
+        let delta = if in_first { delta } else { delta.saturating_neg() };
+        *ratio = ratio.saturating_add_signed(delta).clamp(MIN_RATIO, MAX_RATIO);
+        true
+    }
+
+    /// 按比例将区域分为两部分，左右排列时中间留出一列分割线
+    fn split_area(direction: SplitDirection, ratio: usize, area: Area) -> (Area, Area) {
+        let Area { origin, size } = area;
+        match direction {
+            SplitDirection::Horizontal => {
+                #[allow(clippy::integer_division)]
+                let first_height = size.height.saturating_mul(ratio) / 100;
+                let first = Area {
+                    origin,
+                    size: Size { height: first_height, width: size.width },
+                };
+                let second = Area {
+                    origin: Position { col: origin.col, row: origin.row.saturating_add(first_height) },
+                    size: Size { height: size.height.saturating_sub(first_height), width: size.width },
+                };
+                (first, second)
+            }
+            SplitDirection::Vertical => {
+                #[allow(clippy::integer_division)]
+                let first_width = size.width.saturating_mul(ratio) / 100;
+                let first = Area {
+                    origin,
+                    size: Size { height: size.height, width: first_width },
+                };
+                let second = Area {
+                    origin: Position { col: origin.col.saturating_add(first_width).saturating_add(1), row: origin.row },
+                    size: Size { height: size.height, width: size.width.saturating_sub(first_width).saturating_sub(1) },
+                };
+                (first, second)
+            }
+        }
+    }
+
+    /// 递归计算每个叶子节点的区域
+    fn collect_areas(node: &Node, area: Area, result: &mut Vec<(usize, Area)>) {
+        match node {
+            Node::Leaf(id) => result.push((*id, area)),
+            Node::Split { direction, ratio, first, second } => {
+                let (first_area, second_area) = Self::split_area(*direction, *ratio, area);
+                Self::collect_areas(first, first_area, result);
+                Self::collect_areas(second, second_area, result);
+            }
+        }
+    }
+
+    /// 递归计算竖直分割线的位置
+    fn collect_separators(node: &Node, area: Area, result: &mut Vec<(Position, usize)>) {
+        if let Node::Split { direction, ratio, first, second } = node {
+            let (first_area, second_area) = Self::split_area(*direction, *ratio, area);
+            if *direction == SplitDirection::Vertical {
+                result.push((
+                    Position { col: first_area.right(), row: area.origin.row },
+                    area.size.height,
+                ));
+            }
+            Self::collect_separators(first, first_area, result);
+            Self::collect_separators(second, second_area, result);
+        }
+    }
+    // end region
+}
+
+#[test]
+fn test_split_and_close() {
+    let area = Area {
+        origin: Position::default(),
+        size: Size { height: 20, width: 81 },
+    };
+    let mut layout = Layout::new(View::default(), 0);
+    layout.split(SplitDirection::Vertical);
+    layout.split(SplitDirection::Horizontal);
+    let areas = layout.areas(area);
+    assert_eq!(areas.len(), 3);
+    assert_eq!(layout.separators(area).len(), 1);
+    // 左右排列的窗格之间留出一列分割线
+    let right = areas.iter().find(|(id, _)| *id == 1).map(|(_, area)| *area);
+    assert_eq!(right.map(|area| (area.origin.col, area.size.width)), Some((41, 40)));
+
+    layout.focus_direction(PaneDirection::Left, area);
+    assert_eq!(layout.focused_pane().map(|pane| pane.id), Some(0));
+    assert!(layout.close_focused());
+    assert!(layout.close_focused());
+    assert!(!layout.close_focused());
+    assert_eq!(layout.areas(area), vec![(2, area)]);
+}
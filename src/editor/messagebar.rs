@@ -0,0 +1,76 @@
+use std::time::{Duration, Instant};
+use unicode_width::UnicodeWidthChar;
+
+use super::terminal::Terminal;
+
+/// 消息显示时长，超过该时长后消息自动清除
+const DEFAULT_DURATION: Duration = Duration::new(5, 0);
+
+/// 屏幕底部的消息栏，用于向用户显示提示信息
+pub struct MessageBar {
+    current_message: String, // 当前显示的消息
+    set_time: Instant, // 消息设置时间
+    cleared_after_expiry: bool, // 消息过期后是否已经清除
+    needs_redraw: bool, // 是否需要重新渲染
+}
+
+impl MessageBar {
+    /// 设置新的消息
+    pub fn update_message(&mut self, new_message: &str) {
+        new_message.clone_into(&mut self.current_message);
+        self.set_time = Instant::now();
+        self.cleared_after_expiry = false;
+        self.needs_redraw = true;
+    }
+
+    /// 判断消息是否过期
+    fn is_expired(&self) -> bool {
+        Instant::now().duration_since(self.set_time) > DEFAULT_DURATION
+    }
+
+    /// 在指定行渲染消息栏，消息过期后渲染一次空行
+    pub fn render(&mut self, at: usize, width: usize) {
+        if self.is_expired() && !self.cleared_after_expiry {
+            self.needs_redraw = true;
+        }
+        if !self.needs_redraw || width == 0 {
+            return;
+        }
+        let message = if self.is_expired() {
+            self.cleared_after_expiry = true;
+            ""
+        } else {
+            &self.current_message
+        };
+        // 按显示宽度截断，宽字符放不下时整个省略
+        let mut truncated = String::new();
+        let mut message_width: usize = 0;
+        for ch in message.chars() {
+            let ch_width = ch.width().unwrap_or(0);
+            if message_width.saturating_add(ch_width) > width {
+                break;
+            }
+            truncated.push(ch);
+            message_width = message_width.saturating_add(ch_width);
+        }
+        let result = Terminal::print_row(at, &truncated);
+        debug_assert!(result.is_ok(), "Failed to render message bar");
+        self.needs_redraw = false;
+    }
+
+    /// 标记需要重新渲染
+    pub fn mark_redraw(&mut self) {
+        self.needs_redraw = true;
+    }
+}
+
+impl Default for MessageBar {
+    fn default() -> Self {
+        Self {
+            current_message: String::new(),
+            set_time: Instant::now(),
+            cleared_after_expiry: false,
+            needs_redraw: true,
+        }
+    }
+}
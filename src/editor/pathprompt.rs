@@ -0,0 +1,195 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use std::{
+    env,
+    fs::{canonicalize, read_dir},
+    path::{Path, PathBuf, MAIN_SEPARATOR},
+};
+
+use super::{prompt::{Prompt, PromptEvent}, terminal::Position};
+
+/// 补全候选最多显示的个数
+const MAX_SHOWN_COMPLETIONS: usize = 5;
+
+/// 展开路径开头的~，相对路径基于`working_dir`解析
+pub fn resolve_path(input: &str, working_dir: &Path) -> PathBuf {
+    let expanded = match input.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(MAIN_SEPARATOR) => env::var_os("HOME")
+            .map_or_else(|| PathBuf::from(input), |home| {
+                PathBuf::from(home).join(rest.trim_start_matches(MAIN_SEPARATOR))
+            }),
+        _ => PathBuf::from(input),
+    };
+    if expanded.is_absolute() {
+        expanded
+    } else {
+        working_dir.join(expanded)
+    }
+}
+
+/// 用于判断两个路径是否指向同一文件，文件存在时使用规范化的绝对路径
+pub fn file_identity(input: &str, working_dir: &Path) -> PathBuf {
+    let path = resolve_path(input, working_dir);
+    canonicalize(&path).unwrap_or(path)
+}
+
+/// 列出以input开头的文件路径，目录以路径分隔符结尾，结果保持用户输入的目录部分不变
+pub fn complete_path(input: &str, working_dir: &Path) -> Vec<String> {
+    // 分为目录部分和文件名前缀部分
+    let (dir_part, prefix) = match input.rfind(MAIN_SEPARATOR) {
+        Some(index) => input.split_at(index.saturating_add(1)),
+        None => ("", input),
+    };
+    let dir = if dir_part.is_empty() {
+        working_dir.to_path_buf()
+    } else {
+        resolve_path(dir_part, working_dir)
+    };
+    let Ok(entries) = read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut candidates: Vec<String> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            // 没有输入.时不显示隐藏文件
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+            let separator = if is_dir { MAIN_SEPARATOR.to_string() } else { String::new() };
+            Some(format!("{dir_part}{name}{separator}"))
+        })
+        .collect();
+    candidates.sort();
+    candidates
+}
+
+/// 所有候选共同的前缀
+fn common_prefix(candidates: &[String]) -> String {
+    let Some(first) = candidates.first() else {
+        return String::new();
+    };
+    let mut prefix = first.clone();
+    for candidate in candidates.iter().skip(1) {
+        while !candidate.starts_with(&prefix) {
+            prefix.pop();
+        }
+    }
+    prefix
+}
+
+/// 读取文件路径的输入框，按下Tab时根据文件系统补全路径
+pub struct PathPrompt {
+    prompt: Prompt, // 输入框
+    completions: Vec<String>, // 上次Tab补全的候选路径
+    completion_index: Option<usize>, // 当前补全的候选下标，None表示尚未开始循环
+}
+
+impl PathPrompt {
+    /// 创建带提示文本的路径输入框
+    pub fn new(label: &str) -> Self {
+        Self {
+            prompt: Prompt::new(label),
+            completions: Vec::new(),
+            completion_index: None,
+        }
+    }
+
+    /// 打开输入框，initial为初始内容
+    pub fn open(&mut self, initial: &str) {
+        self.prompt.reset();
+        self.prompt.set_value(initial);
+        self.completions.clear();
+        self.completion_index = None;
+    }
+
+    /// 处理按键，Tab时补全路径
+    pub fn handle_key(&mut self, key: KeyEvent, working_dir: &Path) -> PromptEvent {
+        if key.code != KeyCode::Tab {
+            self.completions.clear();
+            self.completion_index = None;
+        }
+        let event = self.prompt.handle_key(key);
+        if matches!(event, PromptEvent::Complete) {
+            self.complete(working_dir);
+        }
+        event
+    }
+
+    /// 第一次按下Tab时补全到所有候选的共同前缀，之后在候选之间循环
+    fn complete(&mut self, working_dir: &Path) {
+        if self.completions.is_empty() {
+            let input = self.prompt.value();
+            self.completions = complete_path(&input, working_dir);
+            let prefix = common_prefix(&self.completions);
+            // 共同前缀比输入更长时先补全共同前缀
+            if prefix.len() > input.len() {
+                self.prompt.set_value(&prefix);
+                if self.completions.len() == 1 {
+                    self.completions.clear();
+                }
+                return;
+            }
+        }
+        if self.completions.is_empty() {
+            return;
+        }
+        let next = self.completion_index.map_or(0, |index| {
+            index.saturating_add(1).checked_rem(self.completions.len()).unwrap_or(0)
+        });
+        if let Some(candidate) = self.completions.get(next).cloned() {
+            self.prompt.set_value(&candidate);
+            self.completion_index = Some(next);
+        }
+    }
+
+    /// 在指定行渲染输入框，有多个候选时在输入之后显示部分候选
+    pub fn render(&mut self, at: usize, width: usize) {
+        let suffix = if self.completions.len() > 1 {
+            let names: Vec<&str> = self
+                .completions
+                .iter()
+                .take(MAX_SHOWN_COMPLETIONS)
+                .map(|candidate| {
+                    let trimmed = candidate.trim_end_matches(MAIN_SEPARATOR);
+                    trimmed.rfind(MAIN_SEPARATOR).map_or(candidate.as_str(), |index| {
+                        candidate.get(index.saturating_add(1)..).unwrap_or(candidate)
+                    })
+                })
+                .collect();
+            let more = if self.completions.len() > MAX_SHOWN_COMPLETIONS { ", ..." } else { "" };
+            format!("  ({}{more})", names.join(", "))
+        } else {
+            String::new()
+        };
+        self.prompt.render(at, width, &suffix);
+    }
+
+    /// 光标在terminal中的位置
+    pub fn caret_position(&self, at: usize) -> Position {
+        self.prompt.caret_position(at)
+    }
+}
+
+#[test]
+fn test_complete_path() {
+    let working_dir = env::temp_dir().join(format!("hecto-complete-{}", std::process::id()));
+    let _ = std::fs::create_dir_all(working_dir.join("src"));
+    let _ = std::fs::write(working_dir.join("main.rs"), "");
+    let _ = std::fs::write(working_dir.join("makefile"), "");
+    let _ = std::fs::write(working_dir.join("src").join("lib.rs"), "");
+
+    assert_eq!(complete_path("ma", &working_dir), vec!["main.rs", "makefile"]);
+    assert_eq!(common_prefix(&complete_path("ma", &working_dir)), "ma");
+    assert_eq!(complete_path("s", &working_dir), vec![format!("src{MAIN_SEPARATOR}")]);
+    assert_eq!(
+        complete_path(&format!("src{MAIN_SEPARATOR}"), &working_dir),
+        vec![format!("src{MAIN_SEPARATOR}lib.rs")]
+    );
+    assert_eq!(resolve_path("a.txt", &working_dir), working_dir.join("a.txt"));
+    // 同一文件的不同写法指向同一文件
+    let same = format!("src{MAIN_SEPARATOR}..{MAIN_SEPARATOR}main.rs");
+    assert_eq!(file_identity(&same, &working_dir), file_identity("main.rs", &working_dir));
+    assert_ne!(file_identity("lib.rs", &working_dir), file_identity("main.rs", &working_dir));
+    let _ = std::fs::remove_dir_all(&working_dir);
+}
@@ -0,0 +1,211 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use super::{
+    terminal::{Position, Terminal},
+    view::Line,
+};
+
+/// 输入框按键处理结果，由使用输入框的一方根据结果执行相应的操作
+pub enum PromptEvent {
+    None,              // 光标移动等不影响内容的操作
+    Changed(String),   // 输入内容发生变化，例如用于增量查找
+    Submitted(String), // 回车提交
+    Cancelled,         // Esc取消
+    Complete,          // 按下Tab，由使用方进行补全
+}
+
+/// 单行输入框，用于命令行、查找、跳转等需要读取用户输入的场景
+/// 输入内容保存在`Line`中，按字素编辑，内容超出宽度时水平滚动，并记录提交过的历史
+#[derive(Default)]
+pub struct Prompt {
+    label: String, // 输入框前的提示文本
+    line: Line, // 输入内容
+    caret: usize, // 光标所在字素下标
+    scroll_offset: usize, // 水平滚动偏移
+    history: Vec<String>, // 提交过的内容，越新的越靠后
+    history_index: Option<usize>, // 正在浏览的历史下标
+    saved_input: String, // 开始浏览历史时保存的输入，浏览到最新时恢复
+}
+
+impl Prompt {
+    /// 创建带提示文本的输入框
+    pub fn new(label: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// 清空输入，准备下一次输入
+    pub fn reset(&mut self) {
+        self.set_value("");
+        self.history_index = None;
+    }
+
+    /// 当前输入内容
+    pub fn value(&self) -> String {
+        self.line.to_string()
+    }
+
+    /// 设置输入内容，光标移动到末尾
+    pub fn set_value(&mut self, value: &str) {
+        self.line = Line::from(value);
+        self.caret = self.line.grapheme_count();
+    }
+
+    /// 处理按键，返回处理结果
+    pub fn handle_key(&mut self, key: KeyEvent) -> PromptEvent {
+        match (key.code, key.modifiers) {
+            (KeyCode::Esc, _) | (KeyCode::Char('c'), KeyModifiers::CONTROL) => PromptEvent::Cancelled,
+            (KeyCode::Enter, _) => {
+                let value = self.value();
+                // 与上一条相同的内容不重复记录
+                if !value.is_empty() && self.history.last() != Some(&value) {
+                    self.history.push(value.clone());
+                }
+                self.history_index = None;
+                PromptEvent::Submitted(value)
+            }
+            (KeyCode::Tab, _) => PromptEvent::Complete,
+            (KeyCode::Left, _) => {
+                self.caret = self.caret.saturating_sub(1);
+                PromptEvent::None
+            }
+            (KeyCode::Right, _) => {
+                self.caret = self.caret.saturating_add(1).min(self.line.grapheme_count());
+                PromptEvent::None
+            }
+            (KeyCode::Home, _) | (KeyCode::Char('a'), KeyModifiers::CONTROL) => {
+                self.caret = 0;
+                PromptEvent::None
+            }
+            (KeyCode::End, _) | (KeyCode::Char('e'), KeyModifiers::CONTROL) => {
+                self.caret = self.line.grapheme_count();
+                PromptEvent::None
+            }
+            (KeyCode::Up, _) => self.history_previous(),
+            (KeyCode::Down, _) => self.history_next(),
+            (KeyCode::Backspace, _) => {
+                if self.caret == 0 {
+                    return PromptEvent::None;
+                }
+                self.caret = self.caret.saturating_sub(1);
+                self.line.delete(self.caret);
+                PromptEvent::Changed(self.value())
+            }
+            (KeyCode::Delete, _) => {
+                if self.caret >= self.line.grapheme_count() {
+                    return PromptEvent::None;
+                }
+                self.line.delete(self.caret);
+                PromptEvent::Changed(self.value())
+            }
+            (KeyCode::Char(ch), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                let old_len = self.line.grapheme_count();
+                self.line.insert_char(ch, self.caret);
+                // 组合字符会与前一个字素合并，此时光标不移动
+                if self.line.grapheme_count() > old_len {
+                    self.caret = self.caret.saturating_add(1);
+                }
+                PromptEvent::Changed(self.value())
+            }
+            _ => PromptEvent::None,
+        }
+    }
+
+    /// 浏览上一条历史
+    fn history_previous(&mut self) -> PromptEvent {
+        let index = match self.history_index {
+            Some(0) => return PromptEvent::None,
+            Some(index) => index.saturating_sub(1),
+            None => {
+                if self.history.is_empty() {
+                    return PromptEvent::None;
+                }
+                self.saved_input = self.value();
+                self.history.len().saturating_sub(1)
+            }
+        };
+        let Some(entry) = self.history.get(index).cloned() else {
+            return PromptEvent::None;
+        };
+        self.set_value(&entry);
+        self.history_index = Some(index);
+        PromptEvent::Changed(entry)
+    }
+
+    /// 浏览下一条历史，超过最新的历史时恢复原来的输入
+    fn history_next(&mut self) -> PromptEvent {
+        let Some(index) = self.history_index else {
+            return PromptEvent::None;
+        };
+        let next = index.saturating_add(1);
+        if let Some(entry) = self.history.get(next).cloned() {
+            self.set_value(&entry);
+            self.history_index = Some(next);
+        } else {
+            let saved_input = self.saved_input.clone();
+            self.set_value(&saved_input);
+            self.history_index = None;
+        }
+        PromptEvent::Changed(self.value())
+    }
+
+    /// 保证光标在可见范围内
+    fn scroll_caret_into_view(&mut self, width: usize) {
+        let caret_col = self.line.width_until(self.caret);
+        if caret_col < self.scroll_offset {
+            self.scroll_offset = caret_col;
+        } else if caret_col >= self.scroll_offset.saturating_add(width) {
+            self.scroll_offset = caret_col.saturating_sub(width).saturating_add(1);
+        }
+    }
+
+    /// 输入区域宽度，为提示文本留出空间
+    fn input_width(&self, width: usize) -> usize {
+        width.saturating_sub(Line::from(&self.label).width())
+    }
+
+    /// 在指定行渲染输入框，suffix显示在输入内容之后（例如补全候选）
+    pub fn render(&mut self, at: usize, width: usize, suffix: &str) {
+        let input_width = self.input_width(width);
+        self.scroll_caret_into_view(input_width);
+        let visible = self
+            .line
+            .get_visible_graphemes(self.scroll_offset..self.scroll_offset.saturating_add(input_width));
+        let text = format!("{}{visible}{suffix}", self.label);
+        let text = Line::from(&text).get_visible_graphemes(0..width);
+        let result = Terminal::print_row(at, &text);
+        debug_assert!(result.is_ok(), "Failed to render prompt");
+    }
+
+    /// 光标在terminal中的位置
+    pub fn caret_position(&self, at: usize) -> Position {
+        let label_width = Line::from(&self.label).width();
+        Position {
+            col: label_width.saturating_add(self.line.width_until(self.caret).saturating_sub(self.scroll_offset)),
+            row: at,
+        }
+    }
+}
+
+#[test]
+fn test_prompt_editing_and_history() {
+    let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+    let mut prompt = Prompt::new("> ");
+    for ch in "héllo".chars() {
+        prompt.handle_key(key(KeyCode::Char(ch)));
+    }
+    prompt.handle_key(key(KeyCode::Left));
+    prompt.handle_key(key(KeyCode::Left));
+    prompt.handle_key(key(KeyCode::Backspace));
+    assert_eq!(prompt.value(), "hélo");
+    assert!(matches!(prompt.handle_key(key(KeyCode::Enter)), PromptEvent::Submitted(value) if value == "hélo"));
+
+    prompt.reset();
+    prompt.handle_key(key(KeyCode::Char('x')));
+    prompt.handle_key(key(KeyCode::Up));
+    assert_eq!(prompt.value(), "hélo");
+    prompt.handle_key(key(KeyCode::Down));
+    assert_eq!(prompt.value(), "x");
+}
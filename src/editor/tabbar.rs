@@ -0,0 +1,137 @@
+use std::{ops::Range, path::Path};
+
+use super::{
+    terminal::{Position, Terminal},
+    view::{Line, View},
+};
+
+/// 单个标签中文件名的最大显示宽度
+const MAX_NAME_WIDTH: usize = 20;
+
+/// 屏幕顶部的标签栏，列出所有打开的buffer
+#[derive(Default)]
+pub struct TabBar {
+    tabs: Vec<(Range<usize>, usize)>, // 上次渲染时每个标签占据的列范围及对应的buffer下标，用于鼠标点击
+}
+
+impl TabBar {
+    /// 在指定行渲染标签栏，当前buffer反色显示，未保存的buffer用+标记
+    /// 标签总宽度超过terminal宽度时，保证当前buffer可见，两侧用<和>提示被省略的标签
+    pub fn render(&mut self, at: usize, width: usize, views: &[View], current: usize) {
+        self.tabs.clear();
+        if width == 0 {
+            return;
+        }
+        let labels: Vec<Line> = views
+            .iter()
+            .enumerate()
+            .map(|(index, view)| Line::from(&Self::label(index, view)))
+            .collect();
+        let (first, last) = Self::visible_range(&labels, current, width);
+
+        let mut text = String::new();
+        let mut col: usize = 0;
+        let mut current_tab = None;
+        if first > 0 {
+            text.push('<');
+            col = 1;
+        }
+        for (index, label) in labels.iter().enumerate().take(last).skip(first) {
+            let label_width = label.width();
+            let range = col..col.saturating_add(label_width);
+            if index == current {
+                current_tab = Some((range.start, label.to_string()));
+            }
+            text.push_str(&label.to_string());
+            self.tabs.push((range, index));
+            col = col.saturating_add(label_width);
+        }
+        if last < labels.len() {
+            text.push_str(&" ".repeat(width.saturating_sub(col).saturating_sub(1)));
+            text.push('>');
+        }
+
+        // terminal过窄时连当前标签也放不下，按显示宽度截断
+        let text = Line::from(&text).get_visible_graphemes(0..width);
+        let mut result = Terminal::print_in_area(Position { col: 0, row: at }, width, &text);
+        if let Some((col, label)) = current_tab {
+            let label = Line::from(&label).get_visible_graphemes(0..width.saturating_sub(col));
+            result = result.and_then(|()| Terminal::print_inverted_at(Position { col, row: at }, &label));
+        }
+        debug_assert!(result.is_ok(), "Failed to render tab bar");
+    }
+
+    /// 返回点击的列对应的buffer下标
+    pub fn buffer_at(&self, col: usize) -> Option<usize> {
+        self.tabs
+            .iter()
+            .find(|(range, _)| range.contains(&col))
+            .map(|(_, index)| *index)
+    }
+
+    /// 生成标签文本，只显示文件名部分，过长的文件名用…截断
+    fn label(index: usize, view: &View) -> String {
+        let name = view.file_name().map_or_else(
+            || "[No Name]".to_string(),
+            |file_name| {
+                Path::new(&file_name)
+                    .file_name()
+                    .map_or(file_name.clone(), |name| name.to_string_lossy().to_string())
+            },
+        );
+        let name_line = Line::from(&name);
+        let name = if name_line.width() > MAX_NAME_WIDTH {
+            let mut truncated = name_line.get_visible_graphemes(0..MAX_NAME_WIDTH.saturating_sub(1));
+            truncated.push('…');
+            truncated
+        } else {
+            name
+        };
+        let modified = if view.is_modified() { "+" } else { "" };
+        format!(" {}:{name}{modified} ", index.saturating_add(1))
+    }
+
+    /// 计算可以显示的标签范围[first, last)，从当前标签开始向两侧扩展，直到放不下为止
+    fn visible_range(labels: &[Line], current: usize, width: usize) -> (usize, usize) {
+        let widths: Vec<usize> = labels.iter().map(Line::width).collect();
+        let total: usize = widths.iter().sum();
+        if total <= width {
+            return (0, labels.len());
+        }
+        // 两侧各预留一列用于显示省略提示
+        let available = width.saturating_sub(2);
+        let current = current.min(labels.len().saturating_sub(1));
+        let mut first = current;
+        let mut last = current.saturating_add(1);
+        let mut used = widths.get(current).copied().unwrap_or(0);
+        loop {
+            let mut extended = false;
+            if let Some(width) = first.checked_sub(1).and_then(|index| widths.get(index)) {
+                if used.saturating_add(*width) <= available {
+                    used = used.saturating_add(*width);
+                    first = first.saturating_sub(1);
+                    extended = true;
+                }
+            }
+            if let Some(width) = widths.get(last) {
+                if used.saturating_add(*width) <= available {
+                    used = used.saturating_add(*width);
+                    last = last.saturating_add(1);
+                    extended = true;
+                }
+            }
+            if !extended {
+                break;
+            }
+        }
+        (first, last)
+    }
+}
+
+#[test]
+fn test_visible_range() {
+    let labels: Vec<Line> = ["aaaa", "bbbb", "cccc", "dddd"].iter().map(|label| Line::from(label)).collect();
+    assert_eq!(TabBar::visible_range(&labels, 0, 16), (0, 4));
+    assert_eq!(TabBar::visible_range(&labels, 3, 10), (2, 4));
+    assert_eq!(TabBar::visible_range(&labels, 1, 10), (0, 2));
+}
@@ -1,17 +1,20 @@
-use crossterm::cursor::{MoveTo, Hide, Show};
+use crossterm::cursor::{position, MoveTo, Hide, Show};
+use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
 use crossterm::{queue, Command};
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode, size, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
-use crossterm::style::Print;
+use crossterm::style::{Print, Stylize};
 use std::io::{stdout, Write, Error};
 use core::fmt::Display;
 
-#[derive(Default, Copy, Clone, Debug)]
+use super::annotation::AnnotationType;
+
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Size {
     pub height: usize,
     pub width: usize,
 }
 
-#[derive(Copy, Clone, Default)]
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq)]
 pub struct Position {
     pub col: usize,
     pub row: usize, 
@@ -34,13 +37,31 @@ impl Terminal {
     pub fn initialize() -> Result<(),   Error> {
         enable_raw_mode()?;
         Self::enter_alternate_screen()?;
+        Self::queue_command(EnableMouseCapture)?;
         Self::clear_screen()?;
         Self::execute()?;
         Ok(())
     }
 
+    /// 在屏幕左上角打印文本并读取光标位置，得到终端实际显示该文本占据的列数，之后清除该行
+    /// 终端不响应光标位置查询时返回错误
+    #[allow(clippy::as_conversions)]
+    pub fn measure_width(text: &str) -> Result<usize, Error> {
+        let origin = Position::default();
+        Self::move_caret_to(origin)?;
+        Self::clear_line()?;
+        Self::print(text)?;
+        Self::execute()?;
+        let (col, _) = position()?;
+        Self::move_caret_to(origin)?;
+        Self::clear_line()?;
+        Self::execute()?;
+        Ok(col as usize)
+    }
+
     /// 关闭
     pub fn terminate() -> Result<(), Error> {
+        Self::queue_command(DisableMouseCapture)?;
         Self::leave_alternate_screen()?;
         Self::show_caret()?;
         Self::execute()?;
@@ -98,6 +119,43 @@ impl Terminal {
         Ok(())
     }
 
+    /// 在指定位置开始的width宽区域内打印文本，先用空格清空区域，不影响区域外的内容
+    pub fn print_in_area(at: Position, width: usize, text: &str) -> Result<(), Error> {
+        Self::move_caret_to(at)?;
+        Self::print(" ".repeat(width))?;
+        Self::move_caret_to(at)?;
+        Self::print(text)?;
+        Ok(())
+    }
+
+    /// 在指定位置开始的width宽区域内依次打印文本片段，带有注释的片段以对应的样式显示
+    pub fn print_annotated_in_area(
+        at: Position,
+        width: usize,
+        pieces: &[(String, Option<AnnotationType>)],
+    ) -> Result<(), Error> {
+        Self::move_caret_to(at)?;
+        Self::print(" ".repeat(width))?;
+        Self::move_caret_to(at)?;
+        for (text, annotation_type) in pieces {
+            match annotation_type {
+                Some(AnnotationType::Selection | AnnotationType::Caret) => Self::print(text.as_str().reverse())?,
+                Some(AnnotationType::MatchingBracket) => Self::print(text.as_str().bold().underlined())?,
+                Some(AnnotationType::Whitespace) => Self::print(text.as_str().dark_grey())?,
+                Some(AnnotationType::TrailingWhitespace) => Self::print(text.as_str().on_red())?,
+                None => Self::print(text)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// 在指定位置以反色打印文本，文本需要预先填充到所需宽度
+    pub fn print_inverted_at(at: Position, text: &str) -> Result<(), Error> {
+        Self::move_caret_to(at)?;
+        Self::print(text.reverse())?;
+        Ok(())
+    }
+
     /// 返回当前终端窗口大小
     pub fn size() -> Result<Size, Error> {
         let (width_u16, height_u16) = size()?;
@@ -105,7 +163,7 @@ impl Terminal {
         let width = width_u16 as usize;
         #[allow(clippy::as_conversions)]
         let height = height_u16 as usize;
-        Ok(Size { width, height })
+        Ok(Size { height, width })
     }
 
     /// 将事件依次放入buffer中，待execute一次执行
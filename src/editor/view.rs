@@ -1,51 +1,500 @@
 use super::{
-    editorcommand::{Direction, EditorCommand}, terminal::{Position, Size, Terminal}
+    annotation::{Annotation, AnnotationType},
+    config::Settings,
+    editorcommand::{Direction, EditorCommand, SelectionKind},
+    filetype::FileType,
+    terminal::{Position, Size, Terminal},
 };
-use std::cmp;
+use std::{cell::RefCell, cmp, io::{Error, ErrorKind}, ops::Range, rc::Rc};
 
+mod block;
 mod buffer;
+mod carets;
+mod history;
 mod line;
-use buffer::Buffer;
-use line::Line;
+use buffer::{Buffer, LineEdit};
+pub use line::Line;
 
 const NAME: &str = env!("CARGO_PKG_NAME");
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-#[derive(Clone, Copy, Default)]
+/// 行号最少占用的位数
+const MIN_LINE_NUMBER_DIGITS: usize = 3;
+
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
 pub struct Location {
     pub grapheme_index: usize, // Line数组中字素下标，当前行第几个字素
     pub line_index: usize, // 行坐标，即在第几行
 }
 
+impl PartialOrd for Location {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Location {
+    /// 按照在文本中的先后顺序比较，先比较行，再比较字素
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        (self.line_index, self.grapheme_index).cmp(&(other.line_index, other.grapheme_index))
+    }
+}
+
+/// 选择，范围为起点到光标之间
+#[derive(Clone, Copy)]
+struct Selection {
+    anchor: Location, // 选择的起点
+    kind: SelectionKind, // 选择的方式
+}
+
+/// 单词移动时字符的类别，同一类别的连续字符组成一个单词
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Whitespace, // 空白和换行
+    Word, // 字母、数字和下划线
+    Punctuation, // 其他字符
+}
+
+/// buffer可以被多个view共享（例如同一文件的两个窗格），任意view的修改对其他view立即可见
+#[derive(Clone)]
 pub struct View {
-    buffer: Buffer, // 存放读取文件内容
+    buffer: Rc<RefCell<Buffer>>, // 存放读取文件内容
     needs_redraw: bool, // 是否需要重新渲染
-    size: Size, // terminal尺寸
+    origin: Position, // view左上角在terminal中的位置
+    size: Size, // view尺寸
     text_location: Location, // 光标在文本中的位置
     scroll_offset: Position, // 光标在view中相对text的偏移量
+    selection: Option<Selection>, // 当前的选择
+    desired_col: Option<(Location, usize)>, // 上下移动时希望保持的显示列，以及上次上下移动到达的位置，光标离开该位置后失效
+    extra_carets: Vec<Location>, // 主光标之外的其他光标，按文本中的先后顺序排列
+    batch_edit: bool, // 是否正在对所有光标执行同一编辑，此时只在开始时记录一次撤销
 }
 
 impl View {
-    /// 对自定义EditorCommand进行处理
+    /// 对自定义`EditorCommand`进行处理
     pub fn handle_command(&mut self, command: EditorCommand) {
         match command {
-            EditorCommand::Move(direction) => self.move_text_location(&direction),
-            EditorCommand::Insert(ch) => self.insert_char(ch),
+            EditorCommand::Move(direction) if !self.extra_carets.is_empty() => self.move_carets(direction),
+            EditorCommand::Move(direction) => self.move_text_location(direction),
+            EditorCommand::Insert(ch) => {
+                // 在矩形选择中输入时替换每一行选中的文本，与之后的输入合并为一步撤销
+                if self.block().is_some() {
+                    self.delete_block(true);
+                    self.buffer.borrow_mut().continue_typing(self.text_location);
+                }
+                self.edit_at_carets(true, |view| view.insert_char(ch));
+            }
+            EditorCommand::InsertNewline => self.edit_at_carets(false, |view| {
+                view.checkpoint();
+                let caret = view.buffer.borrow_mut().insert_newline(view.text_location);
+                view.text_location = caret;
+                view.scroll_text_location_into_view();
+                view.needs_redraw = true;
+            }),
             EditorCommand::Resize(size) => self.resize(size),
-            EditorCommand::Backspace => self.backspace(),
-            EditorCommand::Delete => self.delete(),
-            _ => (),
+            EditorCommand::Backspace | EditorCommand::Delete => {
+                // 矩形选择中有文本时只删除选中的文本，宽度为0时在每一行删除
+                if self.delete_block(true) {
+                    return;
+                }
+                let backspace = matches!(command, EditorCommand::Backspace);
+                self.edit_at_carets(false, |view| {
+                    view.checkpoint();
+                    if backspace {
+                        view.backspace();
+                    } else {
+                        view.delete();
+                    }
+                });
+            }
+            EditorCommand::Indent => self.edit_at_carets(false, |view| view.indent(false)),
+            EditorCommand::Outdent => self.edit_at_carets(false, |view| view.indent(true)),
+            EditorCommand::AddCaretAbove => self.add_caret_vertically(false),
+            EditorCommand::AddCaretBelow => self.add_caret_vertically(true),
+            EditorCommand::AddCaretAt(position) => self.toggle_caret_at(position),
+            EditorCommand::ToggleWhitespace => self.toggle_whitespace(),
+            EditorCommand::Undo => self.undo(false),
+            EditorCommand::Redo => self.undo(true),
+            EditorCommand::DeleteWordBackward => self.edit_at_carets(false, |view| {
+                let start = view.word_left_location(view.text_location);
+                view.delete_text(start, view.text_location);
+            }),
+            EditorCommand::DeleteWordForward => self.edit_at_carets(false, |view| {
+                let end = view.word_right_location(view.text_location);
+                view.delete_text(view.text_location, end);
+            }),
+            EditorCommand::StartSelection(kind) => {
+                self.selection = Some(Selection { anchor: self.text_location, kind });
+                self.needs_redraw = true;
+            }
+            EditorCommand::ClearSelection => {
+                self.selection = None;
+                self.clear_carets();
+                self.needs_redraw = true;
+            }
+            EditorCommand::JumpToLine(line_number) => self.goto_line(line_number),
+            // 以下命令由Editor处理
+            EditorCommand::Quit
+            | EditorCommand::Save
+            | EditorCommand::NextBuffer
+            | EditorCommand::PrevBuffer
+            | EditorCommand::ListBuffers
+            | EditorCommand::CloseBuffer
+            | EditorCommand::Split(_)
+            | EditorCommand::ClosePane
+            | EditorCommand::FocusPane(_)
+            | EditorCommand::FocusNextPane
+            | EditorCommand::ResizePane(_)
+            | EditorCommand::ToggleTabBar
+            | EditorCommand::Click(_)
+            | EditorCommand::OpenCommandLine
+            | EditorCommand::Search
+            | EditorCommand::SearchNext
+            | EditorCommand::SearchPrev
+            | EditorCommand::GotoLine
+            | EditorCommand::SaveAs
+            | EditorCommand::Open
+            | EditorCommand::Copy
+            | EditorCommand::Cut
+            | EditorCommand::Change
+            | EditorCommand::Paste
+            | EditorCommand::PasteAfter
+            | EditorCommand::KillLine
+            | EditorCommand::Yank
+            | EditorCommand::YankPop
+            | EditorCommand::ToggleComment
+            | EditorCommand::AddNextOccurrence => (),
         }
     }
 
-    /// 将文件内容加载到buffer并重新渲染Terminal
-    pub fn load(&mut self, file_name: &str) {
-        if let Ok(buffer) = Buffer::load(file_name) {
-            self.buffer = buffer;
+    /// 将文件内容加载到buffer并重新渲染Terminal，settings为适用于该文件的设置
+    /// 文件不存在时创建一个以该文件名命名的空buffer，保存时再创建文件
+    pub fn load(&mut self, file_name: &str, settings: Settings) -> Result<(), Error> {
+        let mut buffer = match Buffer::load(file_name) {
+            Ok(buffer) => buffer,
+            Err(err) if err.kind() == ErrorKind::NotFound => Buffer::new_file(file_name),
+            Err(err) => return Err(err),
+        };
+        buffer.set_settings(settings);
+        self.buffer = Rc::new(RefCell::new(buffer));
+        self.extra_carets.clear();
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    /// 保存当前buffer到文件，保存时可能删除行末空白和末尾的空行，之后保证光标位置合法
+    pub fn save(&mut self) -> Result<(), Error> {
+        let mut buffer = self.buffer.borrow_mut();
+        buffer.prepare_save(self.text_location);
+        let result = buffer.save();
+        drop(buffer);
+        self.snap_to_valid_location();
+        self.needs_redraw = true;
+        result
+    }
+
+    /// 以新的文件名保存buffer，保存成功后之后的保存都使用新文件名
+    pub fn save_as(&mut self, file_name: &str) -> Result<(), Error> {
+        let mut buffer = self.buffer.borrow_mut();
+        buffer.prepare_save(self.text_location);
+        let result = buffer.save_as(file_name);
+        drop(buffer);
+        self.snap_to_valid_location();
+        self.needs_redraw = true;
+        result
+    }
+
+    /// buffer的文件类型
+    pub fn file_type(&self) -> FileType {
+        self.buffer.borrow().file_type
+    }
+
+    /// buffer对应的文件名
+    pub fn file_name(&self) -> Option<String> {
+        self.buffer.borrow().file_name.clone()
+    }
+
+    /// buffer是否有未保存的修改
+    pub fn is_modified(&self) -> bool {
+        self.buffer.borrow().dirty
+    }
+
+    /// 光标在文本中的位置
+    pub const fn text_location(&self) -> Location {
+        self.text_location
+    }
+
+    /// 跳转到指定行（从1开始）的行首，超出范围时跳转到最后一行
+    pub fn goto_line(&mut self, line_number: usize) {
+        let last_line = self.buffer.borrow().height().saturating_sub(1);
+        self.extra_carets.clear();
+        self.text_location = Location {
+            grapheme_index: 0,
+            line_index: line_number.saturating_sub(1).min(last_line),
+        };
+        self.scroll_text_location_into_view();
+        self.needs_redraw = true;
+    }
+
+    /// 将光标移动到指定位置
+    pub fn set_text_location(&mut self, location: Location) {
+        self.extra_carets.clear();
+        self.text_location = location;
+        self.snap_to_valid_location();
+        self.needs_redraw = true;
+    }
+
+    /// 从指定位置开始查找query并将光标移动到匹配处，没有找到时返回false
+    pub fn search_from(&mut self, query: &str, from: Location) -> bool {
+        let found = self.buffer.borrow().search_forward(query, from);
+        if let Some(location) = found {
+            self.set_text_location(location);
+            return true;
+        }
+        false
+    }
+
+    /// 从光标处查找query的下一个或上一个匹配并将光标移动到匹配处，到达文件末尾或开头后继续从另一端查找
+    /// 光标已经在匹配处时跳过该匹配；没有找到时返回false
+    pub fn search_next(&mut self, query: &str, forward: bool) -> bool {
+        let Location { grapheme_index, line_index } = self.text_location;
+        let buffer = self.buffer.borrow();
+        let found = if forward {
+            buffer.search_forward(query, Location { grapheme_index: grapheme_index.saturating_add(1), line_index })
+        } else {
+            buffer.search_backward(query, self.text_location)
+        };
+        drop(buffer);
+        if let Some(location) = found {
+            self.set_text_location(location);
+            return true;
+        }
+        false
+    }
+
+    /// buffer的设置
+    pub fn settings(&self) -> Settings {
+        self.buffer.borrow().settings
+    }
+
+    /// 修改buffer的设置
+    pub fn set_settings(&mut self, settings: Settings) {
+        self.buffer.borrow_mut().set_settings(settings);
+        self.scroll_text_location_into_view();
+        self.needs_redraw = true;
+    }
+
+    /// 切换是否显示空白字符
+    fn toggle_whitespace(&mut self) {
+        let mut settings = self.settings();
+        settings.show_whitespace = !settings.show_whitespace;
+        self.set_settings(settings);
+    }
+
+    /// 撤销或重做最近一步修改，光标回到修改时的位置
+    fn undo(&mut self, redo: bool) {
+        self.clear_carets();
+        let caret = self.text_location;
+        let restored = if redo {
+            self.buffer.borrow_mut().redo()
+        } else {
+            self.buffer.borrow_mut().undo(caret)
+        };
+        if let Some(caret) = restored {
+            self.selection = None;
+            self.text_location = caret;
+            self.snap_to_valid_location();
             self.needs_redraw = true;
         }
     }
 
+    /// 替换buffer中所有匹配的文本，返回替换次数
+    pub fn replace_all(&mut self, from: &str, to: &str) -> usize {
+        self.checkpoint();
+        let count = self.buffer.borrow_mut().replace_all(from, to);
+        self.snap_to_valid_location();
+        self.needs_redraw = true;
+        count
+    }
+
+    /// 光标是否在括号上
+    fn is_on_bracket(&self) -> bool {
+        let Location { grapheme_index, line_index } = self.text_location;
+        self.buffer
+            .borrow()
+            .lines
+            .get(line_index)
+            .and_then(|line| line.grapheme(grapheme_index))
+            .is_some_and(|grapheme| matches!(grapheme, "(" | ")" | "[" | "]" | "{" | "}"))
+    }
+
+    /// 光标到行末之间的字素个数，光标在行末时为0
+    pub fn graphemes_to_line_end(&self) -> usize {
+        let Location { grapheme_index, line_index } = self.text_location;
+        self.buffer
+            .borrow()
+            .lines
+            .get(line_index)
+            .map_or(0, Line::grapheme_count)
+            .saturating_sub(grapheme_index)
+    }
+
+    /// 选中的文本，以及是否为按行选择；按行选择时每一行都以换行符结尾
+    pub fn selected_text(&self) -> Option<(String, bool)> {
+        let selection = self.selection?;
+        if selection.kind == SelectionKind::Block {
+            return Some((self.block_text()?, false));
+        }
+        let (start, end) = self.selection_range()?;
+        let buffer = self.buffer.borrow();
+        if selection.kind == SelectionKind::Lines {
+            let text = (start.line_index..end.line_index)
+                .filter_map(|line_index| buffer.lines.get(line_index))
+                .fold(String::new(), |text, line| text + &line.to_string() + "\n");
+            return Some((text, true));
+        }
+        Some((buffer.text_in_range(start, end), false))
+    }
+
+    /// 取消选择并将光标移动到选择的起点
+    pub fn collapse_selection(&mut self) {
+        if let Some((start, _)) = self.selection_range() {
+            self.text_location = start;
+            self.scroll_text_location_into_view();
+        }
+        self.selection = None;
+        self.needs_redraw = true;
+    }
+
+    /// 删除选中的文本并取消选择，光标移动到选择的起点
+    /// 按行选择且`keep_line`为true时只删除各行的内容，保留一个空行
+    /// 矩形选择删除每一行选中的文本，`keep_line`为true时在每一行放置光标以便输入
+    pub fn delete_selection(&mut self, keep_line: bool) {
+        let (Some(selection), Some((start, end))) = (self.selection, self.selection_range()) else {
+            return;
+        };
+        if selection.kind == SelectionKind::Block {
+            self.delete_block(keep_line);
+            return;
+        }
+        self.checkpoint();
+        self.selection = None;
+        let mut buffer = self.buffer.borrow_mut();
+        if selection.kind == SelectionKind::Lines {
+            let last_line = end.line_index.saturating_sub(1);
+            if keep_line {
+                let line_end = Location {
+                    grapheme_index: buffer.lines.get(last_line).map_or(0, Line::grapheme_count),
+                    line_index: last_line,
+                };
+                buffer.delete_range(start, line_end);
+            } else {
+                buffer.delete_lines(start.line_index, end.line_index);
+            }
+        } else {
+            buffer.delete_range(start, end);
+        }
+        drop(buffer);
+        self.text_location = start;
+        self.snap_to_valid_location();
+        self.needs_redraw = true;
+    }
+
+    /// 粘贴文本，`after`为true时粘贴到光标之后
+    /// 按行复制的文本粘贴到当前行之前或之后，光标移动到粘贴的第一行行首；否则光标移动到粘贴的文本之后
+    pub fn paste(&mut self, text: &str, linewise: bool, after: bool) {
+        self.edit_at_carets(false, |view| view.paste_at_caret(text, linewise, after));
+    }
+
+    /// 在光标处粘贴文本
+    fn paste_at_caret(&mut self, text: &str, linewise: bool, after: bool) {
+        self.checkpoint();
+        let Location { grapheme_index, line_index } = self.text_location;
+        let mut buffer = self.buffer.borrow_mut();
+        let height = buffer.height();
+        if linewise {
+            let target = if after { line_index.saturating_add(1).min(height) } else { line_index };
+            let lines = text.strip_suffix('\n').unwrap_or(text);
+            if height == 0 {
+                buffer.insert_text(Location::default(), lines);
+            } else if target >= height {
+                // 粘贴到最后一行之后，先在最后一行末尾换行
+                let last_line = height.saturating_sub(1);
+                let end = Location {
+                    grapheme_index: buffer.lines.get(last_line).map_or(0, Line::grapheme_count),
+                    line_index: last_line,
+                };
+                buffer.insert_text(end, &format!("\n{lines}"));
+            } else {
+                buffer.insert_text(Location { grapheme_index: 0, line_index: target }, text);
+            }
+            self.text_location = Location { grapheme_index: 0, line_index: target };
+        } else {
+            let line_length = buffer.lines.get(line_index).map_or(0, Line::grapheme_count);
+            let at = if after && grapheme_index < line_length {
+                Location { grapheme_index: grapheme_index.saturating_add(1), line_index }
+            } else {
+                self.text_location
+            };
+            self.text_location = buffer.insert_text(at, text);
+        }
+        drop(buffer);
+        self.snap_to_valid_location();
+        self.needs_redraw = true;
+    }
+
+    /// 删除光标到行末的文本并返回，光标在行末时删除换行符
+    /// 只作用于主光标，额外的光标被移除
+    pub fn kill_line(&mut self) -> String {
+        self.clear_carets();
+        self.checkpoint();
+        let start = self.text_location;
+        let mut buffer = self.buffer.borrow_mut();
+        let line_length = buffer.lines.get(start.line_index).map_or(0, Line::grapheme_count);
+        let end = if start.grapheme_index < line_length {
+            Location { grapheme_index: line_length, line_index: start.line_index }
+        } else if start.line_index.saturating_add(1) < buffer.height() {
+            Location { grapheme_index: 0, line_index: start.line_index.saturating_add(1) }
+        } else {
+            return String::new();
+        };
+        let text = buffer.text_in_range(start, end);
+        buffer.delete_range(start, end);
+        drop(buffer);
+        self.needs_redraw = true;
+        text
+    }
+
+    /// 将start（包含）到end（不包含）之间的文本替换为text，作为一步撤销，光标移动到插入的文本之后
+    pub fn replace_text(&mut self, start: Location, end: Location, text: &str) {
+        self.checkpoint();
+        let mut buffer = self.buffer.borrow_mut();
+        buffer.delete_range(start, end);
+        self.text_location = buffer.insert_text(start, text);
+        drop(buffer);
+        self.selection = None;
+        self.snap_to_valid_location();
+        self.needs_redraw = true;
+    }
+
+    /// 删除start（包含）到end（不包含）之间的文本，光标移动到start
+    pub fn delete_text(&mut self, start: Location, end: Location) {
+        if start >= end {
+            return;
+        }
+        self.checkpoint();
+        self.buffer.borrow_mut().delete_range(start, end);
+        self.selection = None;
+        self.text_location = start;
+        self.snap_to_valid_location();
+        self.needs_redraw = true;
+    }
+
+    /// 标记需要重新渲染，切换buffer或共享buffer被其他view修改后调用
+    pub fn mark_redraw(&mut self) {
+        self.needs_redraw = true;
+    }
+
     /// terminal大小发生变化，对size进行修改，移动光标，重新渲染view
     pub fn resize(&mut self, to: Size) {
         self.size = to;
@@ -53,47 +502,213 @@ impl View {
         self.needs_redraw = true;
     }
 
+    /// 设置view在terminal中占据的区域，区域发生变化时重新渲染
+    pub fn set_area(&mut self, origin: Position, size: Size) {
+        if self.origin != origin || self.size != size {
+            self.origin = origin;
+            self.resize(size);
+        }
+    }
+
+    /// 共享的buffer可能被其他view修改，修改后保证光标和选择起点仍然合法
+    pub fn snap_to_valid_location(&mut self) {
+        self.snap_to_valid_line();
+        self.snap_to_valid_grapheme();
+        self.snap_extra_carets();
+        if let Some(selection) = self.selection.as_mut() {
+            let anchor = selection.anchor;
+            selection.anchor = Self::clamp_location(&self.buffer.borrow(), anchor);
+        }
+        self.scroll_text_location_into_view();
+    }
+
+    /// 将位置限制在buffer的范围内，行坐标最大为最后一行之后，列坐标最大为行末
+    fn clamp_location(buffer: &Buffer, location: Location) -> Location {
+        let line_index = location.line_index.min(buffer.height());
+        let line_length = buffer.lines.get(line_index).map_or(0, Line::grapheme_count);
+        Location { grapheme_index: location.grapheme_index.min(line_length), line_index }
+    }
+
     /// 文本内容操作
-    /// 向文本中插入字符
+    /// 向文本中插入字符，在行首输入闭括号时减少一级缩进
+    /// 开启自动闭合时，输入开括号或引号同时插入对应的闭合字符，输入光标处已有的闭合字符时直接跳过
     fn insert_char(&mut self, ch: char) {
+        let Location { grapheme_index, line_index } = self.text_location;
+        let mut buffer = self.buffer.borrow_mut();
+        if !self.batch_edit {
+            buffer.checkpoint_typing(self.text_location);
+        }
+        let pairs = if buffer.settings.auto_close { buffer.file_type.auto_close_pairs() } else { &[] };
+        let line = buffer.lines.get(line_index);
+        let grapheme_at = |index: usize| line.and_then(|line| line.grapheme(index)).and_then(|grapheme| grapheme.chars().next());
+        let next = grapheme_at(grapheme_index);
+        let prev = grapheme_index.checked_sub(1).and_then(grapheme_at);
+        if next == Some(ch) && pairs.iter().any(|(_, close)| *close == ch) {
+            drop(buffer);
+            self.move_text_location(Direction::Right);
+            self.buffer.borrow_mut().continue_typing(self.text_location);
+            return;
+        }
+        // 只在光标之后为空白、行末或闭合字符时自动闭合；引号之前为字母数字时不闭合，例如英文中的撇号
+        let closer = pairs.iter().find(|(open, _)| *open == ch).map(|(_, close)| *close).filter(|close| {
+            if line.is_none() {
+                return false;
+            }
+            let next_allows = next.is_none_or(|next| next.is_whitespace() || pairs.iter().any(|(_, close)| *close == next));
+            let prev_allows = *close != ch || prev.is_none_or(|prev| !prev.is_alphanumeric());
+            next_allows && prev_allows
+        });
+        if let Some(closer) = closer {
+            buffer.insert_char(closer, self.text_location);
+        }
+        let at_indent = buffer.lines.get(line_index).is_some_and(|line| {
+            grapheme_index > 0 && line.substring(0..grapheme_index).chars().all(|ch| ch == ' ' || ch == '\t')
+        });
+        if at_indent && buffer.file_type.dedents_on(ch) {
+            let removed = buffer.outdent_line(line_index);
+            self.text_location.grapheme_index = grapheme_index.saturating_sub(removed);
+        }
         // 获取插入字符所在行原本长度
-        let old_len = self
-            .buffer
-            .lines
-            .get(self.text_location.line_index)
-            .map_or(0, Line::grapheme_count);
+        let old_len = buffer.lines.get(line_index).map_or(0, Line::grapheme_count);
         // 向line中插入字符
-        self.buffer.insert_char(ch, self.text_location);
-        // 获取插入字符后line长度
-        let new_len = self
-            .buffer
-            .lines
-            .get(self.text_location.line_index)
-            .map_or(0, Line::grapheme_count);
-        // 获取插入字素后宽度变化
+        buffer.insert_char(ch, self.text_location);
+        // 获取插入字符后line长度，在最后一行之后输入时新行可能带有缩进
+        let new_len = buffer.lines.get(line_index).map_or(0, Line::grapheme_count);
+        drop(buffer);
+        // 插入字素后光标移动到插入的字素之后，与前一个字素组合时不移动
         let grapheme_delta = new_len.saturating_sub(old_len);
-        // 如果插入字符后宽度发生变化，则将光标向右移动一格
         if grapheme_delta > 0 {
-            self.move_text_location(&Direction::Right);
+            self.text_location.grapheme_index = self.text_location.grapheme_index.saturating_add(grapheme_delta);
+            self.scroll_text_location_into_view();
         }
+        self.buffer.borrow_mut().continue_typing(self.text_location);
         // 重新绘制当前view
         self.needs_redraw = true;
     }
 
+    /// 在修改buffer之前记录当前内容，用于撤销；对所有光标执行同一编辑时已经在开始时记录
+    fn checkpoint(&self) {
+        if self.batch_edit {
+            return;
+        }
+        self.buffer.borrow_mut().checkpoint(self.text_location);
+    }
+
+    /// 选择跨越多行或按行选择时，选择涉及的行；终点在行首时不包含该行
+    fn selected_lines(&self) -> Option<Range<usize>> {
+        let selection = self.selection?;
+        let (start, end) = self.selection_range()?;
+        if selection.kind == SelectionKind::Lines {
+            return Some(start.line_index..end.line_index);
+        }
+        if start.line_index == end.line_index {
+            return None;
+        }
+        let last_line = if end.grapheme_index == 0 { end.line_index } else { end.line_index.saturating_add(1) };
+        Some(start.line_index..last_line)
+    }
+
+    /// 增加或减少缩进，选择跨越多行时对每一行操作并保留选择，作为一步撤销
+    /// 没有选择多行时，增加缩进在光标处插入一级缩进，减少缩进作用于当前行
+    fn indent(&mut self, outdent: bool) {
+        let lines = match self.selected_lines() {
+            Some(lines) => lines,
+            None if outdent => self.text_location.line_index..self.text_location.line_index.saturating_add(1),
+            None => {
+                self.checkpoint();
+                let mut buffer = self.buffer.borrow_mut();
+                let unit = buffer.indent_unit();
+                let caret = buffer.insert_text(self.text_location, &unit);
+                drop(buffer);
+                self.selection = None;
+                self.text_location = caret;
+                self.scroll_text_location_into_view();
+                self.needs_redraw = true;
+                return;
+            }
+        };
+        self.checkpoint();
+        let mut buffer = self.buffer.borrow_mut();
+        let edits: Vec<LineEdit> = lines
+            .map(|line_index| {
+                let count = if outdent { buffer.outdent_line(line_index) } else { buffer.indent_line(line_index) };
+                LineEdit { line_index, grapheme_index: 0, count }
+            })
+            .collect();
+        drop(buffer);
+        self.shift_by_edits(&edits, !outdent);
+        self.scroll_text_location_into_view();
+        self.needs_redraw = true;
+    }
+
+    /// 切换当前行或选中各行以及其他光标所在行的行注释，保留选择，作为一步撤销
+    /// 文件类型没有行注释时返回false
+    pub fn toggle_comment(&mut self) -> bool {
+        let line_index = self.text_location.line_index;
+        let mut lines: Vec<usize> = self
+            .selected_lines()
+            .unwrap_or(line_index..line_index.saturating_add(1))
+            .chain(self.extra_carets.iter().map(|caret| caret.line_index))
+            .collect();
+        lines.sort_unstable();
+        lines.dedup();
+        let toggled = self.buffer.borrow_mut().toggle_comment(lines, self.text_location);
+        let Some((added, changes)) = toggled else {
+            return false;
+        };
+        self.shift_by_edits(&changes, added);
+        self.scroll_text_location_into_view();
+        self.needs_redraw = true;
+        true
+    }
+
+    /// 光标、其他光标和选择起点随各行开头部分插入或删除的文本移动，位于行首的保持在行首以保留整行选择
+    fn shift_by_edits(&mut self, edits: &[LineEdit], inserted: bool) {
+        let shift = |location: &mut Location| {
+            for edit in edits.iter().filter(|edit| edit.line_index == location.line_index) {
+                let (col, count) = (edit.grapheme_index, edit.count);
+                if inserted && location.grapheme_index >= col && location.grapheme_index > 0 {
+                    location.grapheme_index = location.grapheme_index.saturating_add(count);
+                } else if !inserted && location.grapheme_index > col {
+                    let removed = location.grapheme_index.saturating_sub(col).min(count);
+                    location.grapheme_index = location.grapheme_index.saturating_sub(removed);
+                }
+            }
+        };
+        shift(&mut self.text_location);
+        self.extra_carets.iter_mut().for_each(shift);
+        if let Some(selection) = self.selection.as_mut() {
+            shift(&mut selection.anchor);
+        }
+    }
+
     /// 向后删除字符
     fn delete(&mut self) {
         // 在line中delete字符
-        self.buffer.delete(self.text_location);
+        self.buffer.borrow_mut().delete(self.text_location);
         self.needs_redraw = true;
     }
 
     /// 向前删除字符
+    /// 光标位于一对自动闭合的字符之间时同时删除两个字符
     fn backspace(&mut self) {
         if self.text_location.line_index == 0 && self.text_location.grapheme_index == 0 {
             return;
         }
+        let Location { grapheme_index, line_index } = self.text_location;
+        let buffer = self.buffer.borrow();
+        let pair = buffer.lines.get(line_index).and_then(|line| {
+            let prev = line.grapheme(grapheme_index.checked_sub(1)?)?.chars().next()?;
+            let next = line.grapheme(grapheme_index)?.chars().next()?;
+            Some((prev, next))
+        });
+        let in_empty_pair = buffer.settings.auto_close && pair.is_some_and(|pair| buffer.file_type.auto_close_pairs().contains(&pair));
+        drop(buffer);
+        if in_empty_pair {
+            self.delete();
+        }
         // 光标向左移动
-        self.move_text_location(&Direction::Left);
+        self.move_text_location(Direction::Left);
         // 删除光标位置的字符
         self.delete();
     }
@@ -101,6 +716,7 @@ impl View {
     // region: Rendering
 
     /// 渲染整个窗口，如果buffer中有内容，在渲染buffer中内容，否则渲染默认欢迎内容
+    /// 自动换行时一行文本可能占据多个显示行，显示行号时每行前留出行号栏
     pub fn render(&mut self) {
         if !self.needs_redraw {
             return ;
@@ -111,33 +727,149 @@ impl View {
         }
         #[allow(clippy::integer_division)]
         let vertical_center = height / 3;
-        // y轴偏移量
-        let top = self.scroll_offset.row;
-
-        for current_row in 0..height {
-            if let Some(line) = self.buffer.lines.get(current_row.saturating_add(top)) {
-                // 根据偏移量和Terminal宽度从buffer中截取需要渲染到view中的内容
-                let left = self.scroll_offset.col;
-                let right = self.scroll_offset.col.saturating_add(width);
-                Self::render_line(current_row, &line.get_visible_graphemes(left..right));
-            } else if current_row == vertical_center && self.buffer.is_empty() {
-                // 当buffer为空且当前为正中时渲染欢迎内容
-                Self::render_line(current_row, &Self::build_welcome_message(width));
+        let gutter_width = self.gutter_width();
+        let text_width = self.text_width();
+
+        let buffer = self.buffer.borrow();
+        // 光标在括号上时同时高亮与之匹配的括号
+        let brackets: Vec<Location> = buffer
+            .matching_bracket(self.text_location)
+            .map_or_else(Vec::new, |matching| vec![self.text_location, matching]);
+        // 从view顶部对应的行开始渲染
+        let mut line_index = self.scroll_offset.row;
+        let mut current_row = 0;
+        while current_row < height {
+            if let Some(line) = buffer.lines.get(line_index) {
+                let row_starts = self.row_starts(line);
+                for (segment, start) in row_starts.iter().enumerate() {
+                    if current_row >= height {
+                        break;
+                    }
+                    // 根据偏移量和view宽度从line中截取需要渲染到view中的内容
+                    let range = if buffer.settings.soft_wrap {
+                        let left = line.width_until(*start);
+                        // 最后一个显示行多留一列，用于显示选中的换行符
+                        let right = row_starts
+                            .get(segment.saturating_add(1))
+                            .map_or_else(|| line.width().saturating_add(1), |next| line.width_until(*next));
+                        left..right.min(left.saturating_add(text_width))
+                    } else {
+                        self.scroll_offset.col..self.scroll_offset.col.saturating_add(text_width)
+                    };
+                    // 只在一行文本的第一个显示行显示行号
+                    let gutter = if gutter_width == 0 {
+                        String::new()
+                    } else if segment == 0 {
+                        format!("{:>width$} ", line_index.saturating_add(1), width = gutter_width.saturating_sub(1))
+                    } else {
+                        " ".repeat(gutter_width)
+                    };
+                    let mut pieces = vec![(gutter, None)];
+                    let annotations = self.annotations(line_index, line, &brackets);
+                    pieces.extend(line.get_annotated_graphemes(range, &annotations, buffer.settings.show_whitespace));
+                    self.render_annotated_line(current_row, &pieces);
+                    current_row = current_row.saturating_add(1);
+                }
+                line_index = line_index.saturating_add(1);
             } else {
-                // 空白行
-                Self::render_line(current_row, "~");
+                if current_row == vertical_center && buffer.is_empty() {
+                    // 当buffer为空且当前为正中时渲染欢迎内容
+                    self.render_line(current_row, &Self::build_welcome_message(width));
+                } else {
+                    // 空白行
+                    self.render_line(current_row, "~");
+                }
+                current_row = current_row.saturating_add(1);
             }
         }
+        drop(buffer);
         // 每次渲染完之后关闭重复渲染
         self.needs_redraw = false;
     }
 
-    /// 渲染指定行内容
-    fn render_line(at: usize, line_text: &str) {
-        let result = Terminal::print_row(at, line_text);
+    /// 行号栏宽度，包括行号之后的一个空格，不显示行号时为0
+    fn gutter_width(&self) -> usize {
+        let buffer = self.buffer.borrow();
+        if !buffer.settings.line_numbers {
+            return 0;
+        }
+        let digits = buffer.height().max(1).to_string().len();
+        digits.max(MIN_LINE_NUMBER_DIGITS).saturating_add(1)
+    }
+
+    /// 显示文本的宽度，即view宽度减去行号栏宽度
+    fn text_width(&self) -> usize {
+        self.size.width.saturating_sub(self.gutter_width())
+    }
+
+    /// 一行文本在view中每个显示行起始的字素下标，不自动换行时只有一个显示行
+    fn row_starts(&self, line: &Line) -> Vec<usize> {
+        if self.buffer.borrow().settings.soft_wrap {
+            line.wrap(self.text_width())
+        } else {
+            vec![0]
+        }
+    }
+
+    /// 渲染指定行内容，行号相对于view的左上角
+    fn render_line(&self, at: usize, line_text: &str) {
+        let position = Position {
+            col: self.origin.col,
+            row: self.origin.row.saturating_add(at),
+        };
+        let result = Terminal::print_in_area(position, self.size.width, line_text);
         debug_assert!(result.is_ok(), "Failed to render lines");
     }
 
+    /// 渲染由多个片段组成的一行，带有注释的片段以对应的样式显示
+    fn render_annotated_line(&self, at: usize, pieces: &[(String, Option<AnnotationType>)]) {
+        let position = Position {
+            col: self.origin.col,
+            row: self.origin.row.saturating_add(at),
+        };
+        let result = Terminal::print_annotated_in_area(position, self.size.width, pieces);
+        debug_assert!(result.is_ok(), "Failed to render lines");
+    }
+
+    /// 一行文本中需要以特殊样式显示的部分
+    fn annotations(&self, line_index: usize, line: &Line, brackets: &[Location]) -> Vec<Annotation> {
+        let mut annotations = if self.buffer.borrow().settings.show_whitespace {
+            line.whitespace_annotations()
+        } else {
+            Vec::new()
+        };
+        for bracket in brackets.iter().filter(|bracket| bracket.line_index == line_index) {
+            let range = line.grapheme_cols(bracket.grapheme_index);
+            annotations.push(Annotation { range, annotation_type: AnnotationType::MatchingBracket });
+        }
+        // 含有从右向左的文本时，逻辑上连续的选择在显示时可能分为多段
+        let mut selected = Vec::new();
+        if let Some((lines, cols)) = self.block() {
+            if lines.contains(&line_index) {
+                selected = line.cols_of(Self::block_graphemes(line, &cols));
+            }
+        } else if let Some((start, end)) = self.selection_range() {
+            if start.line_index <= line_index && line_index <= end.line_index {
+                let from = if line_index == start.line_index { start.grapheme_index } else { 0 };
+                let to = if line_index == end.line_index { end.grapheme_index } else { line.grapheme_count() };
+                selected = line.cols_of(from..to);
+                // 选择跨过行末时，行末的换行符也显示为选中
+                if line_index != end.line_index {
+                    selected.push(line.grapheme_cols(line.grapheme_count()));
+                }
+            }
+        }
+        for range in selected {
+            annotations.push(Annotation { range, annotation_type: AnnotationType::Selection });
+        }
+        for caret in self.extra_carets.iter().filter(|caret| caret.line_index == line_index) {
+            // 行末的光标显示在换行符的位置
+            let range = line.grapheme_cols(caret.grapheme_index);
+            annotations.push(Annotation { range, annotation_type: AnnotationType::Caret });
+        }
+        annotations
+    }
+
     /// 自定义buffer为空时显示内容，显示版本信息
     fn build_welcome_message(width: usize) -> String {
         if width == 0 {
@@ -176,7 +908,7 @@ impl View {
 
     /// 光标水平移动
     fn scroll_horizontally(&mut self, to: usize) {
-        let Size { width, .. } = self.size;
+        let width = self.text_width();
         let offset_changed = if to < self.scroll_offset.col {
             self.scroll_offset.col = to;
             true
@@ -191,26 +923,81 @@ impl View {
 
     // 由文本中的坐标定位视图中的坐标
     fn scroll_text_location_into_view(&mut self) {
+        if self.buffer.borrow().settings.soft_wrap {
+            self.scroll_wrapped_into_view();
+            return;
+        }
         let Position { row, col } = self.text_location_to_position();
         self.scroll_vertically(row);
         self.scroll_horizontally(col);
     }
+
+    /// 自动换行时不需要水平滚动，垂直方向以整行为单位滚动，直到光标所在显示行出现在view中
+    fn scroll_wrapped_into_view(&mut self) {
+        let Size { height, .. } = self.size;
+        let line_index = self.text_location.line_index;
+        let mut top = self.scroll_offset.row.min(line_index);
+        while top < line_index && self.wrapped_caret_position(top).row >= height {
+            top = top.saturating_add(1);
+        }
+        let offset_changed = top != self.scroll_offset.row || self.scroll_offset.col != 0;
+        self.scroll_offset = Position { col: 0, row: top };
+        self.needs_redraw = self.needs_redraw || offset_changed;
+    }
     // end region
 
     // region: Location and Position
 
-    /// 获取光标在view中的相对位置位置，即view的显示坐标
+    /// 获取光标在terminal中的位置，即view的显示坐标加上行号栏宽度和view的起点
     pub fn crate_position(&self) -> Position {
-        self.text_location_to_position().saturating_sub(self.scroll_offset)
+        let Position { col, row } = if self.buffer.borrow().settings.soft_wrap {
+            self.wrapped_caret_position(self.scroll_offset.row)
+        } else {
+            self.text_location_to_position().saturating_sub(self.scroll_offset)
+        };
+        Position {
+            col: col.saturating_add(self.gutter_width()).saturating_add(self.origin.col),
+            row: row.saturating_add(self.origin.row),
+        }
+    }
+
+    /// 自动换行时，从第top行开始显示的情况下光标在view中的位置
+    fn wrapped_caret_position(&self, top: usize) -> Position {
+        let buffer = self.buffer.borrow();
+        let text_width = self.text_width();
+        let Location { grapheme_index, line_index } = self.text_location;
+        // 光标所在行之前的每一行可能占据多个显示行
+        let rows_before = (top..line_index).fold(0, |rows: usize, index| {
+            rows.saturating_add(buffer.lines.get(index).map_or(1, |line| line.wrap(text_width).len()))
+        });
+        let Some(line) = buffer.lines.get(line_index) else {
+            return Position { col: 0, row: rows_before };
+        };
+        // 按显示列查找光标所在的显示行，含有从右向左的文本时字素下标与显示位置不单调对应
+        let caret_col = line.width_until(grapheme_index);
+        let (segment, start_col) = line
+            .wrap(text_width)
+            .into_iter()
+            .map(|start| line.width_until(start))
+            .enumerate()
+            .rev()
+            .find(|(_, start_col)| *start_col <= caret_col)
+            .unwrap_or((0, 0));
+        let col = caret_col.saturating_sub(start_col);
+        Position {
+            // 光标在显示行末尾时停留在最后一列
+            col: col.min(text_width.saturating_sub(1)),
+            row: rows_before.saturating_add(segment),
+        }
     }
 
     /// 将文本中的位置Location 转换为 Position
     fn text_location_to_position(&self) -> Position {
         let row = self.text_location.line_index;
-        let col = self.buffer.lines.get(row).map_or(0, |line| {
+        let col = self.buffer.borrow().lines.get(row).map_or(0, |line| {
             line.width_until(self.text_location.grapheme_index)
         });
-        Position { row, col }
+        Position { col, row }
     }
     // end region
 
@@ -218,8 +1005,9 @@ impl View {
 
     /// 文本中光标位置移动，然后将光标在terminal中进行移动
     #[allow(clippy::arithmetic_side_effects)]
-    fn move_text_location(&mut self, direction: &Direction) {
+    fn move_text_location(&mut self, direction: Direction) {
         let Size { height, .. } = self.size;
+        let was_on_bracket = self.is_on_bracket();
         match direction {
             Direction::Up => self.move_up(1),
             Direction::Down => self.move_down(1),
@@ -229,22 +1017,50 @@ impl View {
             Direction::End => self.move_to_end_of_line(),
             Direction::PageUP => self.move_up(height.saturating_sub(1)),
             Direction::PageDown => self.move_down(height.saturating_sub(1)),
+            Direction::NextWordStart => self.move_to_next_word_start(),
+            Direction::NextWordStartInLine => self.move_to_next_word_start_in_line(),
+            Direction::PrevWordStart => self.move_to_prev_word_start(),
+            Direction::WordEnd => self.move_to_word_end(),
+            Direction::WordLeft => self.text_location = self.word_left_location(self.text_location),
+            Direction::WordRight => self.text_location = self.word_right_location(self.text_location),
+            Direction::PrevParagraph => self.move_to_prev_paragraph(),
+            Direction::NextParagraph => self.move_to_next_paragraph(),
+            Direction::DocumentStart => self.text_location = Location::default(),
+            Direction::DocumentEnd => {
+                let buffer = self.buffer.borrow();
+                let line_index = buffer.height().saturating_sub(1);
+                let grapheme_index = buffer.lines.get(line_index).map_or(0, Line::grapheme_count);
+                drop(buffer);
+                self.text_location = Location { grapheme_index, line_index };
+            }
+            Direction::MatchingBracket => {
+                let found = self.buffer.borrow().matching_bracket(self.text_location);
+                if let Some(location) = found {
+                    self.text_location = location;
+                }
+            }
+        }
+        // 移动光标会改变选择的范围和高亮的括号
+        if self.selection.is_some() || was_on_bracket || self.is_on_bracket() {
+            self.needs_redraw = true;
         }
         self.scroll_text_location_into_view();
     }
 
     /// crate 向上移动step行
     fn move_up(&mut self, step: usize) {
+        let col = self.desired_col();
         self.text_location.line_index = self.text_location.line_index.saturating_sub(step);
-        self.snap_to_valid_grapheme();
+        self.snap_to_col(col);
     }
 
     /// crate 向下移动step行
     #[allow(clippy::arithmetic_side_effects)]
     fn move_down(&mut self, step: usize) {
+        let col = self.desired_col();
         self.text_location.line_index = self.text_location.line_index.saturating_add(step);
         self.snap_to_valid_line();
-        self.snap_to_valid_grapheme();
+        self.snap_to_col(col);
     }
 
     /// 向左移动一格
@@ -260,15 +1076,34 @@ impl View {
 
     /// 向右移动一格
     fn move_right(&mut self) {
-        let line_width = self.buffer.lines.get(self.text_location.line_index).map_or(0, Line::grapheme_count);
+        let line_width = self.buffer.borrow().lines.get(self.text_location.line_index).map_or(0, Line::grapheme_count);
         if self.text_location.grapheme_index < line_width {
-            self.text_location.grapheme_index += 1;
+            self.text_location.grapheme_index = self.text_location.grapheme_index.saturating_add(1);
         } else {
             self.move_down(1);
             self.move_to_start_of_line();
         }
     }
 
+    /// 上下移动时希望保持的显示列，光标在上次上下移动到达的位置时沿用之前的列，否则使用光标当前的显示列
+    fn desired_col(&self) -> usize {
+        match self.desired_col {
+            Some((location, col)) if location == self.text_location => col,
+            _ => self.text_location_to_position().col,
+        }
+    }
+
+    /// 将光标移动到当前行中显示列不超过col的最后一个字素，并记录希望保持的显示列
+    fn snap_to_col(&mut self, col: usize) {
+        self.text_location.grapheme_index = self
+            .buffer
+            .borrow()
+            .lines
+            .get(self.text_location.line_index)
+            .map_or(0, |line| line.grapheme_index_at(col));
+        self.desired_col = Some((self.text_location, col));
+    }
+
     /// 移动至当前行行首
     fn move_to_start_of_line(&mut self) {
         self.text_location.grapheme_index = 0;
@@ -276,19 +1111,255 @@ impl View {
 
     /// 移动至当前行行末
     fn move_to_end_of_line(&mut self) {
-        self.text_location.grapheme_index = self.buffer.lines.get(self.text_location.line_index).map_or(0, Line::grapheme_count);
+        self.text_location.grapheme_index = self.buffer.borrow().lines.get(self.text_location.line_index).map_or(0, Line::grapheme_count);
+    }
+
+    /// 按Unicode单词边界向左查找上一个单词的开头，位于行首时跨到上一行行末
+    fn word_left_location(&self, from: Location) -> Location {
+        let buffer = self.buffer.borrow();
+        let Location { grapheme_index, line_index } = from;
+        if grapheme_index == 0 {
+            return match line_index.checked_sub(1) {
+                Some(prev_line) => Location {
+                    grapheme_index: buffer.lines.get(prev_line).map_or(0, Line::grapheme_count),
+                    line_index: prev_line,
+                },
+                None => from,
+            };
+        }
+        let start = buffer.lines.get(line_index).map_or(0, |line| {
+            line.word_ranges()
+                .iter()
+                .rev()
+                .map(|range| range.start)
+                .find(|start| *start < grapheme_index)
+                .unwrap_or(0)
+        });
+        Location { grapheme_index: start, line_index }
+    }
+
+    /// 按Unicode单词边界向右查找下一个单词的结尾，位于行末时跨到下一行行首
+    fn word_right_location(&self, from: Location) -> Location {
+        let buffer = self.buffer.borrow();
+        let Location { grapheme_index, line_index } = from;
+        let Some(line) = buffer.lines.get(line_index) else {
+            return from;
+        };
+        if grapheme_index >= line.grapheme_count() {
+            let next_line = line_index.saturating_add(1);
+            return if next_line < buffer.height() {
+                Location { grapheme_index: 0, line_index: next_line }
+            } else {
+                from
+            };
+        }
+        let end = line
+            .word_ranges()
+            .iter()
+            .map(|range| range.end)
+            .find(|end| *end > grapheme_index)
+            .unwrap_or_else(|| line.grapheme_count());
+        Location { grapheme_index: end, line_index }
+    }
+
+    /// 移动到上一个段落之前的空行，没有时移动到文件开头
+    fn move_to_prev_paragraph(&mut self) {
+        let buffer = self.buffer.borrow();
+        let is_blank = |line_index: usize| buffer.lines.get(line_index).is_none_or(Line::is_blank);
+        let mut line_index = self.text_location.line_index.min(buffer.height());
+        // 先跳过光标之前的空行，再跳过段落内容
+        while line_index > 0 && is_blank(line_index.saturating_sub(1)) {
+            line_index = line_index.saturating_sub(1);
+        }
+        while line_index > 0 && !is_blank(line_index.saturating_sub(1)) {
+            line_index = line_index.saturating_sub(1);
+        }
+        drop(buffer);
+        self.text_location = Location { grapheme_index: 0, line_index: line_index.saturating_sub(1) };
+    }
+
+    /// 移动到下一个段落之后的空行，没有时移动到文件末尾
+    fn move_to_next_paragraph(&mut self) {
+        let buffer = self.buffer.borrow();
+        let height = buffer.height();
+        let is_blank = |line_index: usize| buffer.lines.get(line_index).is_none_or(Line::is_blank);
+        let mut line_index = self.text_location.line_index.saturating_add(1);
+        while line_index < height && is_blank(line_index) {
+            line_index = line_index.saturating_add(1);
+        }
+        while line_index < height && !is_blank(line_index) {
+            line_index = line_index.saturating_add(1);
+        }
+        let location = if line_index < height {
+            Location { grapheme_index: 0, line_index }
+        } else {
+            let last_line = height.saturating_sub(1);
+            Location { grapheme_index: buffer.lines.get(last_line).map_or(0, Line::grapheme_count), line_index: last_line }
+        };
+        drop(buffer);
+        self.text_location = location;
+    }
+
+    /// 移动到下一个单词的开头，空行也算作一个单词
+    fn move_to_next_word_start(&mut self) {
+        let origin = self.text_location;
+        let mut location = origin;
+        let class = self.char_class(location);
+        // 跳过当前单词
+        if class != CharClass::Whitespace {
+            while self.char_class(location) == class {
+                let Some(next) = self.next_location(location) else {
+                    self.text_location = location;
+                    return;
+                };
+                location = next;
+            }
+        }
+        // 跳过空白
+        while self.char_class(location) == CharClass::Whitespace {
+            if location != origin && self.is_empty_line(location.line_index) {
+                break;
+            }
+            let Some(next) = self.next_location(location) else {
+                break;
+            };
+            location = next;
+        }
+        self.text_location = location;
+    }
+
+    /// 移动到下一个单词的开头，下一个单词在之后的行时移动到当前行的行末，用于dw等操作符不删除换行符
+    fn move_to_next_word_start_in_line(&mut self) {
+        let line_index = self.text_location.line_index;
+        self.move_to_next_word_start();
+        if self.text_location.line_index != line_index {
+            self.text_location.line_index = line_index;
+            self.move_to_end_of_line();
+        }
+    }
+
+    /// 移动到上一个单词的开头
+    fn move_to_prev_word_start(&mut self) {
+        let Some(mut location) = self.prev_location(self.text_location) else {
+            return;
+        };
+        while self.char_class(location) == CharClass::Whitespace && !self.is_empty_line(location.line_index) {
+            let Some(prev) = self.prev_location(location) else {
+                break;
+            };
+            location = prev;
+        }
+        let class = self.char_class(location);
+        if class != CharClass::Whitespace {
+            while let Some(prev) = self.prev_location(location) {
+                if self.char_class(prev) != class {
+                    break;
+                }
+                location = prev;
+            }
+        }
+        self.text_location = location;
+    }
+
+    /// 移动到单词的最后一个字素，已经在单词结尾时移动到下一个单词的结尾
+    fn move_to_word_end(&mut self) {
+        let Some(mut location) = self.next_location(self.text_location) else {
+            return;
+        };
+        while self.char_class(location) == CharClass::Whitespace {
+            let Some(next) = self.next_location(location) else {
+                self.text_location = location;
+                return;
+            };
+            location = next;
+        }
+        let class = self.char_class(location);
+        while let Some(next) = self.next_location(location) {
+            if self.char_class(next) != class {
+                break;
+            }
+            location = next;
+        }
+        self.text_location = location;
+    }
+
+    /// 指定位置字素的类别，行末视为换行符
+    fn char_class(&self, location: Location) -> CharClass {
+        let buffer = self.buffer.borrow();
+        let grapheme = buffer.lines.get(location.line_index).map_or_else(String::new, |line| {
+            line.substring(location.grapheme_index..location.grapheme_index.saturating_add(1))
+        });
+        match grapheme.chars().next() {
+            None => CharClass::Whitespace,
+            Some(ch) if ch.is_whitespace() => CharClass::Whitespace,
+            Some(ch) if ch.is_alphanumeric() || ch == '_' => CharClass::Word,
+            Some(_) => CharClass::Punctuation,
+        }
+    }
+
+    /// 指定行是否为空行
+    fn is_empty_line(&self, line_index: usize) -> bool {
+        self.buffer.borrow().lines.get(line_index).is_some_and(|line| line.grapheme_count() == 0)
+    }
+
+    /// 文本中的下一个位置，行末的下一个位置为下一行行首，已经在文本末尾时返回None
+    fn next_location(&self, location: Location) -> Option<Location> {
+        let buffer = self.buffer.borrow();
+        let line_length = buffer.lines.get(location.line_index)?.grapheme_count();
+        if location.grapheme_index < line_length {
+            Some(Location { grapheme_index: location.grapheme_index.saturating_add(1), ..location })
+        } else if location.line_index.saturating_add(1) < buffer.height() {
+            Some(Location { grapheme_index: 0, line_index: location.line_index.saturating_add(1) })
+        } else {
+            None
+        }
+    }
+
+    /// 文本中的上一个位置，行首的上一个位置为上一行行末，已经在文本开头时返回None
+    fn prev_location(&self, location: Location) -> Option<Location> {
+        if location.grapheme_index > 0 {
+            return Some(Location { grapheme_index: location.grapheme_index.saturating_sub(1), ..location });
+        }
+        let line_index = location.line_index.checked_sub(1)?;
+        let grapheme_index = self.buffer.borrow().lines.get(line_index).map_or(0, Line::grapheme_count);
+        Some(Location { grapheme_index, line_index })
+    }
+
+    /// 选择的范围，起点在前；包含光标所在字素时终点向后移动一个位置，按行选择时为涉及的整行
+    fn selection_range(&self) -> Option<(Location, Location)> {
+        let selection = self.selection?;
+        let start = selection.anchor.min(self.text_location);
+        let end = selection.anchor.max(self.text_location);
+        match selection.kind {
+            SelectionKind::Exclusive => Some((start, end)),
+            SelectionKind::Inclusive => Some((start, self.next_location(end).unwrap_or(end))),
+            SelectionKind::Lines => Some((
+                Location { grapheme_index: 0, line_index: start.line_index },
+                Location { grapheme_index: 0, line_index: end.line_index.saturating_add(1) },
+            )),
+            // 矩形选择的范围为左上角到右下角
+            SelectionKind::Block => {
+                let (lines, cols) = self.block()?;
+                let buffer = self.buffer.borrow();
+                let corner = |line_index: usize, at_end: bool| {
+                    let graphemes = buffer.lines.get(line_index).map_or(0..0, |line| Self::block_graphemes(line, &cols));
+                    Location { grapheme_index: if at_end { graphemes.end } else { graphemes.start }, line_index }
+                };
+                Some((corner(lines.start, false), corner(lines.end.saturating_sub(1), true)))
+            }
+        }
     }
 
     /// 保证列坐标是合法的
     fn snap_to_valid_grapheme(&mut self) {
-        self.text_location.grapheme_index = self.buffer.lines.get(self.text_location.line_index).map_or(0, |line| {
+        self.text_location.grapheme_index = self.buffer.borrow().lines.get(self.text_location.line_index).map_or(0, |line| {
             cmp::min(line.grapheme_count(), self.text_location.grapheme_index)
-        })
+        });
     }
 
     /// 保证当前行坐标是合法的
     fn snap_to_valid_line(&mut self) {
-        self.text_location.line_index = cmp::min(self.text_location.line_index, self.buffer.height());
+        self.text_location.line_index = cmp::min(self.text_location.line_index, self.buffer.borrow().height());
     }
 }
 
@@ -296,11 +1367,44 @@ impl Default for View {
     /// 实现view的default，默认初始化View，之后可能会改
     fn default() -> Self {
         Self {
-            buffer: Buffer::default(),
+            buffer: Rc::default(),
             needs_redraw: true,
+            origin: Position::default(),
             size: Terminal::size().unwrap_or_default(),
             text_location: Location::default(),
             scroll_offset: Position::default(),
+            selection: None,
+            desired_col: None,
+            extra_carets: Vec::new(),
+            batch_edit: false,
         }
     }
-}
\ No newline at end of file
+}
+
+/// 共享buffer的另一个view删除文本后，光标和选择起点被限制在文本范围内
+#[test]
+fn test_shared_buffer() {
+    let mut view = View::default();
+    view.buffer.borrow_mut().insert_text(Location::default(), "abc\ndef\ng");
+    let mut other = view.clone();
+    other.text_location = Location { grapheme_index: 3, line_index: 1 };
+    other.selection = Some(Selection { anchor: Location { grapheme_index: 2, line_index: 0 }, kind: SelectionKind::Exclusive });
+    view.delete_text(Location { grapheme_index: 1, line_index: 0 }, Location { grapheme_index: 3, line_index: 1 });
+    other.snap_to_valid_location();
+    assert_eq!(other.text_location, Location { grapheme_index: 1, line_index: 1 });
+    assert_eq!(other.selection.map(|selection| selection.anchor), Some(Location { grapheme_index: 1, line_index: 0 }));
+}
+
+#[test]
+fn test_replace_text() {
+    let mut view = View::default();
+    view.buffer.borrow_mut().insert_text(Location::default(), "ab\ncd");
+    let start = Location { grapheme_index: 1, line_index: 0 };
+    view.text_location = Location { grapheme_index: 1, line_index: 1 };
+    view.replace_text(start, view.text_location, "xy");
+    assert_eq!(view.buffer.borrow().text(), vec!["axyd"]);
+    assert_eq!(view.text_location, Location { grapheme_index: 3, line_index: 0 });
+    // 删除和插入作为一步撤销
+    view.handle_command(EditorCommand::Undo);
+    assert_eq!(view.buffer.borrow().text(), vec!["ab", "cd"]);
+}
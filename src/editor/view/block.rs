@@ -0,0 +1,107 @@
+use super::{super::editorcommand::SelectionKind, Line, Location, View};
+use std::ops::Range;
+
+impl View {
+    /// 矩形选择涉及的行和显示列，显示列由选择起点的显示位置和光标上下移动时保持的显示列决定，不包含右边界
+    /// 光标经过较短的行时矩形的宽度不变
+    pub(super) fn block(&self) -> Option<(Range<usize>, Range<usize>)> {
+        let selection = self.selection.filter(|selection| selection.kind == SelectionKind::Block)?;
+        let buffer = self.buffer.borrow();
+        let anchor_col = buffer
+            .lines
+            .get(selection.anchor.line_index)
+            .map_or(0, |line| line.width_until(selection.anchor.grapheme_index));
+        let caret_col = self.desired_col();
+        let top = selection.anchor.line_index.min(self.text_location.line_index);
+        let bottom = selection.anchor.line_index.max(self.text_location.line_index);
+        Some((top..bottom.saturating_add(1), anchor_col.min(caret_col)..anchor_col.max(caret_col)))
+    }
+
+    /// 一行中位于显示列范围内的字素，宽字符只要部分在范围内就包含在内
+    pub(super) fn block_graphemes(line: &Line, cols: &Range<usize>) -> Range<usize> {
+        let start = line.grapheme_index_at(cols.start);
+        if cols.is_empty() {
+            return start..start;
+        }
+        let end = line
+            .grapheme_index_at(cols.end.saturating_sub(1))
+            .saturating_add(1)
+            .min(line.grapheme_count());
+        start..end.max(start)
+    }
+
+    /// 矩形选择中的文本，每行一段，以换行符分隔
+    pub(super) fn block_text(&self) -> Option<String> {
+        let (lines, cols) = self.block()?;
+        let buffer = self.buffer.borrow();
+        let rows: Vec<String> = lines
+            .filter_map(|line_index| buffer.lines.get(line_index))
+            .map(|line| line.substring(Self::block_graphemes(line, &cols)))
+            .collect();
+        Some(rows.join("\n"))
+    }
+
+    /// 删除矩形选择中的文本并取消选择，光标移动到矩形第一行的左边界；有文本被删除时返回true
+    /// `with_carets`为true时在每行矩形的左边界处放置光标，之后的编辑作用于每一行，
+    /// 长度不及左边界的行不放置光标，主光标保持在原来的行
+    pub(super) fn delete_block(&mut self, with_carets: bool) -> bool {
+        let Some((lines, cols)) = self.block() else {
+            return false;
+        };
+        self.checkpoint();
+        self.selection = None;
+        let primary_line = if with_carets { self.text_location.line_index } else { lines.start };
+        let mut buffer = self.buffer.borrow_mut();
+        let mut deleted = false;
+        let mut carets = Vec::new();
+        for line_index in lines {
+            let Some(line) = buffer.lines.get(line_index) else {
+                continue;
+            };
+            if line.width() < cols.start && line_index != primary_line {
+                continue;
+            }
+            let range = Self::block_graphemes(line, &cols);
+            let start = Location { grapheme_index: range.start, line_index };
+            if !range.is_empty() {
+                buffer.delete_range(start, Location { grapheme_index: range.end, line_index });
+                deleted = true;
+            }
+            if line_index == primary_line {
+                self.text_location = start;
+            } else if with_carets {
+                carets.push(start);
+            }
+        }
+        drop(buffer);
+        self.extra_carets = carets;
+        self.scroll_text_location_into_view();
+        self.needs_redraw = true;
+        deleted
+    }
+}
+
+#[test]
+fn test_block_selection() {
+    use super::{super::editorcommand::EditorCommand, Selection};
+    let mut view = View::default();
+    view.buffer.borrow_mut().insert_text(Location::default(), "a中b|x\nabcd|x\n\nabcd");
+    view.selection = Some(Selection { anchor: Location { grapheme_index: 1, line_index: 0 }, kind: SelectionKind::Block });
+    view.text_location = Location { grapheme_index: 3, line_index: 3 };
+    assert_eq!(view.selected_text(), Some(("中\nbc\n\nbc".to_string(), false)));
+    view.handle_command(EditorCommand::Insert('Z'));
+    view.handle_command(EditorCommand::Insert('Y'));
+    assert_eq!(view.buffer.borrow().text(), vec!["aZYb|x", "aZYd|x", "", "aZYd"]);
+    view.handle_command(EditorCommand::Undo);
+    assert_eq!(view.buffer.borrow().text(), vec!["a中b|x", "abcd|x", "", "abcd"]);
+
+    // 经过较短的行时右边界保持上下移动前的显示列，剪切只删除文本而不添加光标
+    view.selection = Some(Selection { anchor: Location { grapheme_index: 1, line_index: 1 }, kind: SelectionKind::Block });
+    view.text_location = Location { grapheme_index: 3, line_index: 1 };
+    view.handle_command(EditorCommand::Move(super::Direction::Down));
+    assert_eq!(view.selected_text(), Some(("bc\n".to_string(), false)));
+    view.delete_selection(false);
+    assert_eq!(view.buffer.borrow().text(), vec!["a中b|x", "ad|x", "", "abcd"]);
+    assert!(view.extra_carets.is_empty());
+    assert_eq!(view.text_location, Location { grapheme_index: 1, line_index: 1 });
+}
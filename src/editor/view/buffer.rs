@@ -1,22 +1,530 @@
-use std::io::Error;
-use std::fs::read_to_string;
+use std::io::{Error, Write};
+use std::fs::{read_to_string, File};
+use std::ops::Range;
 use super::line::Line;
+use super::history::{Change, History};
 use super::Location;
+use super::super::{config::{Charset, IndentStyle, LineEnding, Settings}, filetype::FileType};
 
-#[derive(Default, Debug)]
+/// 查找匹配的括号时最多扫描的行数
+const MAX_BRACKET_SCAN_LINES: usize = 10_000;
+
+/// 对一行开头部分的修改，用于调整光标和选择的位置
+#[derive(Debug, PartialEq, Eq)]
+pub struct LineEdit {
+    pub line_index: usize, // 修改的行
+    pub grapheme_index: usize, // 修改开始的字素下标
+    pub count: usize, // 插入或删除的字素个数
+}
+
+/// UTF-8文件开头的BOM
+const BOM: char = '\u{feff}';
+
+#[derive(Debug, Default)]
 pub struct Buffer {
     pub lines: Vec<Line>,
+    pub file_name: Option<String>, // 对应的文件名，新建buffer时为None
+    pub dirty: bool, // 是否有未保存的修改
+    pub settings: Settings, // 适用于该buffer的设置
+    pub bom: bool, // 加载的文件开头是否有BOM
+    pub line_ending: LineEnding, // 加载的文件使用的换行符，由第一行的结尾判断
+    pub file_type: FileType, // 根据文件名识别的文件类型
+    history: History, // 修改历史，用于撤销和重做
 }
 
 impl Buffer {
-    /// 将文件内容加载到buffer
+    /// 将文件内容加载到buffer，文件开头的BOM不作为文本内容，并记录文件使用的换行符
     pub fn load(file_name: &str) -> Result<Self, Error>{
         let contents = read_to_string(file_name)?;
+        let (contents, bom) = match contents.strip_prefix(BOM) {
+            Some(contents) => (contents, true),
+            None => (contents.as_str(), false),
+        };
+        let line_ending = match contents.split_once('\n') {
+            Some((first, _)) if first.ends_with('\r') => LineEnding::Crlf,
+            _ => LineEnding::Lf,
+        };
         let mut lines = Vec::new();
         for value in contents.lines() {
             lines.push(Line::from(value));
         }
-        Ok(Self { lines })
+        Ok(Self {
+            lines,
+            file_name: Some(file_name.to_string()),
+            file_type: FileType::from_file_name(file_name),
+            bom,
+            line_ending,
+            ..Self::default()
+        })
+    }
+
+    /// 创建以`file_name`命名的空buffer，保存时再创建文件
+    pub fn new_file(file_name: &str) -> Self {
+        Self {
+            file_name: Some(file_name.to_string()),
+            file_type: FileType::from_file_name(file_name),
+            ..Self::default()
+        }
+    }
+
+    /// 创建使用当前制表符宽度的新行
+    fn new_line(&self, line_str: &str) -> Line {
+        let mut line = Line::from(line_str);
+        line.set_tab_width(self.settings.tab_width);
+        line
+    }
+
+    /// 修改buffer的设置，并将制表符宽度应用到所有行
+    pub fn set_settings(&mut self, settings: Settings) {
+        self.settings = settings;
+        for line in &mut self.lines {
+            line.set_tab_width(settings.tab_width);
+        }
+    }
+
+    /// 将所有from替换为to，返回替换的次数
+    pub fn replace_all(&mut self, from: &str, to: &str) -> usize {
+        if from.is_empty() {
+            return 0;
+        }
+        let mut count: usize = 0;
+        for index in 0..self.lines.len() {
+            let Some(line) = self.lines.get(index) else {
+                continue;
+            };
+            let line_str = line.to_string();
+            let matches = line_str.matches(from).count();
+            if matches > 0 {
+                let new_line = self.new_line(&line_str.replace(from, to));
+                self.splice_lines(index..index.saturating_add(1), vec![new_line]);
+                count = count.saturating_add(matches);
+            }
+        }
+        count
+    }
+
+    /// 从指定位置开始向后查找query，到达文件末尾后从头继续查找
+    pub fn search_forward(&self, query: &str, from: Location) -> Option<Location> {
+        let height = self.lines.len();
+        // 多检查一次起始行，覆盖起始位置之前的匹配
+        for offset in 0..=height {
+            let line_index = from.line_index.saturating_add(offset).checked_rem(height)?;
+            let from_grapheme_index = if offset == 0 { from.grapheme_index } else { 0 };
+            if let Some(grapheme_index) = self
+                .lines
+                .get(line_index)
+                .and_then(|line| line.search_forward(query, from_grapheme_index))
+            {
+                return Some(Location { grapheme_index, line_index });
+            }
+        }
+        None
+    }
+
+    /// 在指定位置之前向前查找query，到达文件开头后从末尾继续查找
+    pub fn search_backward(&self, query: &str, before: Location) -> Option<Location> {
+        let height = self.lines.len();
+        // 多检查一次起始行，覆盖起始位置之后的匹配
+        for offset in 0..=height {
+            let line_index = before.line_index.saturating_add(height).saturating_sub(offset).checked_rem(height)?;
+            let before_grapheme_index = if offset == 0 { before.grapheme_index } else { usize::MAX };
+            if let Some(grapheme_index) = self
+                .lines
+                .get(line_index)
+                .and_then(|line| line.search_backward(query, before_grapheme_index))
+            {
+                return Some(Location { grapheme_index, line_index });
+            }
+        }
+        None
+    }
+
+    /// 查找与指定位置的括号匹配的括号，指定位置不是括号或没有匹配的括号时返回None
+    /// 开括号向后查找，闭括号向前查找，可以跨越多行；字符串和注释中的括号被忽略
+    /// 最多扫描`MAX_BRACKET_SCAN_LINES`行，避免括号不配对时在大文件中反复扫描
+    pub fn matching_bracket(&self, at: Location) -> Option<Location> {
+        let line_comment = self.file_type.line_comment();
+        let quotes = self.file_type.string_quotes();
+        let code_graphemes = |line: &Line| line.code_graphemes(line_comment, quotes);
+        let line = self.lines.get(at.line_index)?;
+        let mut code = code_graphemes(line);
+        if !code.get(at.grapheme_index).copied().unwrap_or(false) {
+            return None;
+        }
+        let (open, close, forward) = match line.grapheme(at.grapheme_index)? {
+            "(" => ("(", ")", true),
+            "[" => ("[", "]", true),
+            "{" => ("{", "}", true),
+            ")" => ("(", ")", false),
+            "]" => ("[", "]", false),
+            "}" => ("{", "}", false),
+            _ => return None,
+        };
+        let (same, other) = if forward { (open, close) } else { (close, open) };
+        let mut depth: usize = 0;
+        let mut line_index = at.line_index;
+        let mut grapheme_index = at.grapheme_index;
+        for _ in 0..MAX_BRACKET_SCAN_LINES {
+            let line = self.lines.get(line_index)?;
+            loop {
+                if code.get(grapheme_index).copied().unwrap_or(false) {
+                    let grapheme = line.grapheme(grapheme_index);
+                    if grapheme == Some(same) {
+                        depth = depth.saturating_add(1);
+                    } else if grapheme == Some(other) {
+                        depth = depth.saturating_sub(1);
+                        if depth == 0 {
+                            return Some(Location { grapheme_index, line_index });
+                        }
+                    }
+                }
+                if forward && grapheme_index.saturating_add(1) < line.grapheme_count() {
+                    grapheme_index = grapheme_index.saturating_add(1);
+                } else if !forward && grapheme_index > 0 {
+                    grapheme_index = grapheme_index.saturating_sub(1);
+                } else {
+                    break;
+                }
+            }
+            // 进入下一行或上一行
+            line_index = if forward { line_index.saturating_add(1) } else { line_index.checked_sub(1)? };
+            let next_line = self.lines.get(line_index)?;
+            code = code_graphemes(next_line);
+            grapheme_index = if forward { 0 } else { next_line.grapheme_count().saturating_sub(1) };
+        }
+        None
+    }
+
+    /// 按照设置在保存前修改文本：删除行末空白、删除文件末尾的空行
+    /// 有修改时先开始新的一步撤销，保存时的修改可以撤销
+    pub fn prepare_save(&mut self, caret: Location) {
+        let trim_whitespace = self.settings.trim_trailing_whitespace
+            && self.lines.iter().any(|line| line.to_string().ends_with([' ', '\t']));
+        let trim_blank_lines = self.settings.trim_final_newlines && self.lines.last().is_some_and(Line::is_blank);
+        if !trim_whitespace && !trim_blank_lines {
+            return;
+        }
+        self.checkpoint(caret);
+        if trim_whitespace {
+            self.trim_trailing_whitespace();
+        }
+        if trim_blank_lines {
+            while self.lines.last().is_some_and(Line::is_blank) {
+                let last = self.lines.len().saturating_sub(1);
+                self.splice_lines(last..self.lines.len(), Vec::new());
+            }
+        }
+    }
+
+    /// 删除每一行末尾的空格和制表符
+    fn trim_trailing_whitespace(&mut self) {
+        for index in 0..self.lines.len() {
+            let Some(line) = self.lines.get(index) else {
+                continue;
+            };
+            let line_str = line.to_string();
+            let trimmed = line_str.trim_end_matches([' ', '\t']);
+            if trimmed.len() < line_str.len() {
+                let new_line = self.new_line(trimmed);
+                self.splice_lines(index..index.saturating_add(1), vec![new_line]);
+            }
+        }
+    }
+
+    /// 将buffer内容写回文件，使用设置中的编码和换行符，写入成功后清除修改标记
+    pub fn save(&mut self) -> Result<(), Error> {
+        if let Some(file_name) = &self.file_name {
+            self.write_to(file_name)?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+
+    /// 将buffer内容写入新文件，写入成功后才使用新文件名并根据新文件名重新识别文件类型
+    /// 写入失败时保持原来的文件名，之后的保存仍然写入原来的文件
+    pub fn save_as(&mut self, file_name: &str) -> Result<(), Error> {
+        self.write_to(file_name)?;
+        self.file_name = Some(file_name.to_string());
+        self.file_type = FileType::from_file_name(file_name);
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// 将buffer内容写入指定文件
+    fn write_to(&self, file_name: &str) -> Result<(), Error> {
+        let mut file = File::create(file_name)?;
+        // 没有指定编码时保持文件原有的BOM
+        let bom = self.settings.charset.map_or(self.bom, |charset| charset == Charset::Utf8Bom);
+        if bom {
+            write!(file, "{BOM}")?;
+        }
+        // 没有指定换行符时保持文件原有的换行符
+        let line_ending = self.settings.line_ending.unwrap_or(self.line_ending).as_str();
+        for (index, line) in self.lines.iter().enumerate() {
+            // 最后一行之后是否换行取决于设置
+            let is_last = index.saturating_add(1) == self.lines.len();
+            if is_last && !self.settings.insert_final_newline {
+                write!(file, "{line}")?;
+            } else {
+                write!(file, "{line}{line_ending}")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 获取start（包含）到end（不包含）之间的文本，行之间以换行符连接
+    pub fn text_in_range(&self, start: Location, end: Location) -> String {
+        let mut text = String::new();
+        for line_index in start.line_index..=end.line_index {
+            let Some(line) = self.lines.get(line_index) else {
+                break;
+            };
+            let from = if line_index == start.line_index { start.grapheme_index } else { 0 };
+            let to = if line_index == end.line_index { end.grapheme_index } else { line.grapheme_count() };
+            text.push_str(&line.substring(from..to));
+            if line_index != end.line_index {
+                text.push('\n');
+            }
+        }
+        text
+    }
+
+    /// 删除start（包含）到end（不包含）之间的文本
+    pub fn delete_range(&mut self, start: Location, end: Location) {
+        let Some(first) = self.lines.get(start.line_index) else {
+            return;
+        };
+        let prefix = first.substring(0..start.grapheme_index);
+        let last_index = end.line_index.min(self.lines.len().saturating_sub(1));
+        let suffix = self.lines.get(last_index).map_or_else(String::new, |last| {
+            let from = if end.line_index > last_index { last.grapheme_count() } else { end.grapheme_index };
+            last.substring(from..last.grapheme_count())
+        });
+        let new_line = self.new_line(&format!("{prefix}{suffix}"));
+        self.splice_lines(start.line_index..last_index.saturating_add(1), vec![new_line]);
+    }
+
+    /// 删除指定范围内的整行
+    pub fn delete_lines(&mut self, from: usize, to: usize) {
+        let to = to.min(self.lines.len());
+        if from < to {
+            self.splice_lines(from..to, Vec::new());
+        }
+    }
+
+    /// 在指定位置插入文本，文本中的换行符将行拆分，返回插入的文本之后的位置
+    pub fn insert_text(&mut self, at: Location, text: &str) -> Location {
+        if at.line_index > self.lines.len() || text.is_empty() {
+            return at;
+        }
+        let (prefix, suffix) = self.lines.get(at.line_index).map_or_else(
+            || (String::new(), String::new()),
+            |line| {
+                (
+                    line.substring(0..at.grapheme_index),
+                    line.substring(at.grapheme_index..line.grapheme_count()),
+                )
+            },
+        );
+        let parts: Vec<&str> = text.split('\n').collect();
+        let last_part_index = parts.len().saturating_sub(1);
+        let mut new_lines = Vec::with_capacity(parts.len());
+        let mut end = at;
+        for (index, part) in parts.iter().enumerate() {
+            let mut line_str = if index == 0 { format!("{prefix}{part}") } else { (*part).to_string() };
+            if index == last_part_index {
+                end = Location {
+                    grapheme_index: Line::from(&line_str).grapheme_count(),
+                    line_index: at.line_index.saturating_add(index),
+                };
+                line_str.push_str(&suffix);
+            }
+            new_lines.push(self.new_line(&line_str));
+        }
+        let replaced = if at.line_index < self.lines.len() { at.line_index..at.line_index.saturating_add(1) } else { at.line_index..at.line_index };
+        self.splice_lines(replaced, new_lines);
+        end
+    }
+
+    /// 将`range`中的各行替换为`new_lines`，并记录修改用于撤销
+    fn splice_lines(&mut self, range: Range<usize>, new_lines: Vec<Line>) {
+        let removed = self
+            .lines
+            .get(range.clone())
+            .map_or_else(Vec::new, |lines| lines.iter().map(ToString::to_string).collect());
+        let inserted = new_lines.iter().map(ToString::to_string).collect();
+        self.history.record(Change { line_index: range.start, removed, inserted });
+        self.lines.splice(range, new_lines);
+        self.dirty = true;
+    }
+
+    /// 修改指定的一行，并记录该行修改前后的内容用于撤销
+    fn edit_line(&mut self, line_index: usize, edit: impl FnOnce(&mut Line)) {
+        let Some(line) = self.lines.get_mut(line_index) else {
+            return;
+        };
+        let removed = vec![line.to_string()];
+        edit(line);
+        self.history.record(Change { line_index, removed, inserted: vec![line.to_string()] });
+        self.dirty = true;
+    }
+
+    /// 将从`line_index`开始的`count`行替换为`texts`，用于撤销和重做
+    fn replace_lines(lines: &mut Vec<Line>, line_index: usize, count: usize, texts: &[String], tab_width: usize) {
+        let end = line_index.saturating_add(count).min(lines.len());
+        let new_lines = texts.iter().map(|text| {
+            let mut line = Line::from(text.as_str());
+            line.set_tab_width(tab_width);
+            line
+        });
+        lines.splice(line_index.min(end)..end, new_lines);
+    }
+
+    /// 开始新的一步撤销，之后的修改可以作为一步撤销
+    pub fn checkpoint(&mut self, caret: Location) {
+        self.history.checkpoint(caret);
+    }
+
+    /// 在输入字符之前调用，连续输入的字符作为一步撤销
+    pub fn checkpoint_typing(&mut self, caret: Location) {
+        self.history.checkpoint_typing(caret);
+    }
+
+    /// 记录输入字符之后的光标位置，在该位置继续输入时与之前的输入合并
+    pub fn continue_typing(&mut self, caret: Location) {
+        self.history.continue_typing(caret);
+    }
+
+    /// 撤销最近一步修改，返回修改之前的光标位置，没有可以撤销的修改时返回None
+    pub fn undo(&mut self, caret: Location) -> Option<Location> {
+        let revision = self.history.undo(caret)?;
+        for change in revision.changes.iter().rev() {
+            Self::replace_lines(&mut self.lines, change.line_index, change.inserted.len(), &change.removed, self.settings.tab_width);
+        }
+        self.dirty = true;
+        Some(revision.caret)
+    }
+
+    /// 重做最近一次撤销的修改，返回撤销时的光标位置，没有可以重做的修改时返回None
+    pub fn redo(&mut self) -> Option<Location> {
+        let revision = self.history.redo()?;
+        for change in &revision.changes {
+            Self::replace_lines(&mut self.lines, change.line_index, change.removed.len(), &change.inserted, self.settings.tab_width);
+        }
+        self.dirty = true;
+        Some(revision.redo_caret)
+    }
+
+    /// 在指定行开头增加一级缩进，空行不缩进，返回增加的字素个数
+    pub fn indent_line(&mut self, line_index: usize) -> usize {
+        if self.lines.get(line_index).is_none_or(|line| line.grapheme_count() == 0) {
+            return 0;
+        }
+        let unit = self.indent_unit();
+        self.insert_text(Location { grapheme_index: 0, line_index }, &unit);
+        Line::from(&unit).grapheme_count()
+    }
+
+    /// 切换指定各行的行注释，文件类型没有行注释时返回None，不记录撤销
+    /// 所有非空行都已注释时取消注释，否则在这些行的最小缩进处插入注释符号，空行保持不变
+    /// 修改作为一步撤销，`caret`为撤销后光标回到的位置；返回是否为添加注释，以及每一行的修改
+    pub fn toggle_comment(&mut self, lines: impl IntoIterator<Item = usize>, caret: Location) -> Option<(bool, Vec<LineEdit>)> {
+        let token = self.file_type.line_comment()?;
+        self.checkpoint(caret);
+        let lines: Vec<(usize, String)> = lines
+            .into_iter()
+            .filter_map(|line_index| self.lines.get(line_index).map(|line| (line_index, line.to_string())))
+            .filter(|(_, text)| !text.trim().is_empty())
+            .collect();
+        let indent_of = |text: &str| text.chars().take_while(|ch| *ch == ' ' || *ch == '\t').count();
+        let commented = !lines.is_empty() && lines.iter().all(|(_, text)| text.trim_start().starts_with(token));
+        let min_indent = lines.iter().map(|(_, text)| indent_of(text)).min().unwrap_or(0);
+        let prefix = format!("{token} ");
+        let mut changes = Vec::with_capacity(lines.len());
+        for (line_index, text) in &lines {
+            let (col, count) = if commented {
+                let col = indent_of(text);
+                let rest = text.chars().skip(col).collect::<String>();
+                let count = if rest.starts_with(&prefix) { prefix.chars().count() } else { token.chars().count() };
+                let start = Location { grapheme_index: col, line_index: *line_index };
+                self.delete_range(start, Location { grapheme_index: col.saturating_add(count), ..start });
+                (col, count)
+            } else {
+                self.insert_text(Location { grapheme_index: min_indent, line_index: *line_index }, &prefix);
+                (min_indent, prefix.chars().count())
+            };
+            changes.push(LineEdit { line_index: *line_index, grapheme_index: col, count });
+        }
+        Some((!commented, changes))
+    }
+
+    /// 一级缩进的文本，根据设置使用制表符或空格，必须使用制表符的文件类型总是使用制表符
+    pub fn indent_unit(&self) -> String {
+        if self.file_type.requires_tabs() {
+            return "\t".to_string();
+        }
+        match self.settings.indent_style {
+            IndentStyle::Tabs => "\t".to_string(),
+            IndentStyle::Spaces => " ".repeat(self.settings.indent_size),
+        }
+    }
+
+    /// 指定行开头的空白
+    fn leading_whitespace(&self, line_index: usize) -> String {
+        self.lines.get(line_index).map_or_else(String::new, |line| {
+            line.to_string().chars().take_while(|ch| *ch == ' ' || *ch == '\t').collect()
+        })
+    }
+
+    /// 在指定位置换行，新行沿用当前行的缩进，在开括号等之后增加一级缩进，返回新行中缩进之后的位置
+    /// 光标位于一对括号之间时，闭括号移动到再下一行并保持原来的缩进
+    pub fn insert_newline(&mut self, at: Location) -> Location {
+        let (before, after) = self.lines.get(at.line_index).map_or_else(
+            || (String::new(), String::new()),
+            |line| (line.substring(0..at.grapheme_index), line.substring(at.grapheme_index..line.grapheme_count())),
+        );
+        let base: String = before.chars().take_while(|ch| *ch == ' ' || *ch == '\t').collect();
+        if !self.file_type.indents_after(&before) {
+            return self.insert_text(at, &format!("\n{base}"));
+        }
+        let indent = format!("{base}{}", self.indent_unit());
+        let caret = Location {
+            grapheme_index: Line::from(&indent).grapheme_count(),
+            line_index: at.line_index.saturating_add(1),
+        };
+        if after.trim_start().starts_with(['}', ']', ')']) {
+            self.insert_text(at, &format!("\n{indent}\n{base}"));
+            // 闭括号之前的空白由新行的缩进代替
+            let closer = Location { grapheme_index: Line::from(&base).grapheme_count(), line_index: caret.line_index.saturating_add(1) };
+            let spaces = after.chars().take_while(|ch| ch.is_whitespace()).count();
+            self.delete_range(closer, Location { grapheme_index: closer.grapheme_index.saturating_add(spaces), ..closer });
+        } else {
+            self.insert_text(at, &format!("\n{indent}"));
+        }
+        caret
+    }
+
+    /// 删除指定行开头的一级缩进，返回删除的字素个数
+    pub fn outdent_line(&mut self, line_index: usize) -> usize {
+        let leading = self.leading_whitespace(line_index);
+        let count = if leading.starts_with('\t') {
+            1
+        } else {
+            leading.chars().take_while(|ch| *ch == ' ').count().min(self.settings.indent_size)
+        };
+        if count > 0 {
+            self.delete_range(
+                Location { grapheme_index: 0, line_index },
+                Location { grapheme_index: count, line_index },
+            );
+        }
+        count
+    }
+
+    /// 每一行的文本，用于测试中比较buffer的内容
+    #[cfg(test)]
+    pub fn text(&self) -> Vec<String> {
+        self.lines.iter().map(ToString::to_string).collect()
     }
 
     /// 判断buffer是否为空
@@ -35,11 +543,13 @@ impl Buffer {
         if at.line_index > self.lines.len() {
             return;
         }
-        // 在新一行添加字符
+        // 在新一行添加字符，新行沿用上一行的缩进
         if at.line_index == self.lines.len() {
-            self.lines.push(Line::from(&character.to_string()));
-        } else if let Some(line) = self.lines.get_mut(at.line_index) {
-            line.insert_char(character, at.grapheme_index);
+            let indent = at.line_index.checked_sub(1).map_or_else(String::new, |last| self.leading_whitespace(last));
+            let line = self.new_line(&format!("{indent}{character}"));
+            self.splice_lines(at.line_index..at.line_index, vec![line]);
+        } else {
+            self.edit_line(at.line_index, |line| line.insert_char(character, at.grapheme_index));
         }
     }
 
@@ -48,16 +558,15 @@ impl Buffer {
         // 判断是否在行末
         if at.grapheme_index >= self.lines.get(at.line_index).map_or(0, Line::grapheme_count) {
             // 如果当前行不是最后一行，则将下一行合并到当前行，最后一行不进行任何操作
-            if at.line_index + 1 < self.lines.len() {
-                let next_line = self.lines.remove(at.line_index.saturating_add(1));
-                #[allow(clippy::indexing_slicing)]
-                self.lines[at.line_index].append(&next_line)
+            let next_index = at.line_index.saturating_add(1);
+            if let (Some(line), Some(next_line)) = (self.lines.get(at.line_index), self.lines.get(next_index)) {
+                let mut joined = self.new_line(&line.to_string());
+                joined.append(next_line);
+                self.splice_lines(at.line_index..next_index.saturating_add(1), vec![joined]);
             }
         } else {
             // 不在行末正常删除即可
-            if let Some(line) = self.lines.get_mut(at.line_index) {
-                line.delete(at.grapheme_index);
-            }
+            self.edit_line(at.line_index, |line| line.delete(at.grapheme_index));
         }
     }
 }
@@ -65,4 +574,106 @@ impl Buffer {
 #[test]
 fn test_load() {
 
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_insert_newline() {
+    let mut buffer = Buffer { file_type: FileType::Rust, ..Buffer::default() };
+    buffer.insert_text(Location::default(), "    fn f() {}");
+    let caret = buffer.insert_newline(Location { grapheme_index: 12, line_index: 0 });
+    assert_eq!(caret, Location { grapheme_index: 8, line_index: 1 });
+    assert_eq!(buffer.text(), vec!["    fn f() {", "        ", "    }"]);
+    assert_eq!(buffer.insert_newline(Location { grapheme_index: 2, line_index: 2 }).grapheme_index, 2);
+    assert_eq!(buffer.outdent_line(1), 4);
+    // Makefile总是使用制表符缩进
+    assert_eq!(buffer.indent_unit(), "    ");
+    buffer.file_type = FileType::Makefile;
+    assert_eq!(buffer.indent_unit(), "\t");
+}
+
+#[test]
+fn test_toggle_comment() {
+    let mut buffer = Buffer { file_type: FileType::Python, ..Buffer::default() };
+    buffer.insert_text(Location::default(), "  if x:\n\n    y()");
+    let edits = || vec![LineEdit { line_index: 0, grapheme_index: 2, count: 2 }, LineEdit { line_index: 2, grapheme_index: 2, count: 2 }];
+    assert_eq!(buffer.toggle_comment(0..3, Location::default()), Some((true, edits())));
+    assert_eq!(buffer.text(), vec!["  # if x:", "", "  #   y()"]);
+    assert_eq!(buffer.toggle_comment(0..3, Location::default()), Some((false, edits())));
+    assert_eq!(buffer.text(), vec!["  if x:", "", "    y()"]);
+    buffer.file_type = FileType::Text;
+    assert!(buffer.toggle_comment(0..1, Location::default()).is_none());
+}
+
+#[test]
+fn test_search() {
+    let mut buffer = Buffer::default();
+    buffer.insert_text(Location::default(), "ab ab\nxab");
+    let at = |line_index, grapheme_index| Location { grapheme_index, line_index };
+    assert_eq!(buffer.search_forward("ab", at(0, 1)), Some(at(0, 3)));
+    assert_eq!(buffer.search_forward("ab", at(1, 2)), Some(at(0, 0)));
+    assert_eq!(buffer.search_backward("ab", at(0, 3)), Some(at(0, 0)));
+    assert_eq!(buffer.search_backward("ab", at(0, 0)), Some(at(1, 1)));
+    assert_eq!(buffer.search_backward("ab", at(1, 1)), Some(at(0, 3)));
+    assert_eq!(buffer.search_backward("zz", at(1, 1)), None);
+}
+
+#[test]
+fn test_matching_bracket() {
+    let mut buffer = Buffer::default();
+    buffer.insert_text(Location::default(), "fn f(a: [u8; 2]) {\n    (a)\n}) ]");
+    let at = |line_index, grapheme_index| Location { grapheme_index, line_index };
+    assert_eq!(buffer.matching_bracket(at(0, 4)), Some(at(0, 15)));
+    assert_eq!(buffer.matching_bracket(at(0, 14)), Some(at(0, 8)));
+    assert_eq!(buffer.matching_bracket(at(0, 17)), Some(at(2, 0)));
+    assert_eq!(buffer.matching_bracket(at(2, 0)), Some(at(0, 17)));
+    assert_eq!(buffer.matching_bracket(at(2, 1)), None);
+    assert_eq!(buffer.matching_bracket(at(2, 3)), None);
+    assert_eq!(buffer.matching_bracket(at(0, 0)), None);
+
+    buffer.file_type = FileType::Rust;
+    buffer.insert_text(at(3, 0), "f(\")\", // )\n)");
+    assert_eq!(buffer.matching_bracket(at(3, 1)), Some(at(4, 0)));
+    assert_eq!(buffer.matching_bracket(at(3, 3)), None);
+}
+
+#[test]
+fn test_save_as_failure() {
+    let mut buffer = Buffer::new_file("a.txt");
+    buffer.insert_text(Location::default(), "x");
+    assert!(buffer.save_as("/nonexistent-dir/b.rs").is_err());
+    assert_eq!(buffer.file_name.as_deref(), Some("a.txt"));
+    assert_eq!(buffer.file_type, FileType::Text);
+    assert!(buffer.dirty);
+}
+
+#[test]
+fn test_prepare_save() {
+    let mut buffer = Buffer::default();
+    buffer.settings.trim_trailing_whitespace = true;
+    buffer.settings.trim_final_newlines = true;
+    buffer.insert_text(Location::default(), "a \t\nb\n  \n");
+    let caret = Location { grapheme_index: 2, line_index: 2 };
+    buffer.prepare_save(caret);
+    assert_eq!(buffer.text(), vec!["a", "b"]);
+    assert_eq!(buffer.undo(Location::default()), Some(caret));
+    assert_eq!(buffer.text(), vec!["a \t", "b", "  ", ""]);
+    assert_eq!(buffer.redo(), Some(Location::default()));
+    assert_eq!(buffer.text(), vec!["a", "b"]);
+}
+
+#[test]
+fn test_crlf_round_trip() {
+    let path = std::env::temp_dir().join(format!("hecto-crlf-{}.txt", std::process::id()));
+    let file_name = path.to_string_lossy().to_string();
+    std::fs::write(&path, "a\r\nb\r\n").unwrap();
+    let mut buffer = Buffer::load(&file_name).unwrap();
+    assert_eq!(buffer.line_ending, LineEnding::Crlf);
+    buffer.insert_text(Location { grapheme_index: 1, line_index: 1 }, "c");
+    buffer.save().unwrap();
+    assert_eq!(read_to_string(&path).unwrap(), "a\r\nbc\r\n");
+    // 显式指定的换行符覆盖文件原有的换行符
+    buffer.settings.line_ending = Some(LineEnding::Lf);
+    buffer.save().unwrap();
+    assert_eq!(read_to_string(&path).unwrap(), "a\nbc\n");
+    std::fs::remove_file(&path).unwrap();
+}
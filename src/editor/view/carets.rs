@@ -0,0 +1,285 @@
+use super::{
+    super::{editorcommand::Direction, terminal::Position},
+    Line, Location, View,
+};
+
+impl View {
+    /// 对每个光标执行一次编辑，所有光标的编辑作为一步撤销
+    /// 从文本中最后的光标开始编辑，编辑只修改光标附近的文本，
+    /// 已编辑的光标随编辑增减的行数上下移动，与编辑的光标同一行时按到行末的距离恢复
+    pub(super) fn edit_at_carets(&mut self, typing: bool, mut edit: impl FnMut(&mut Self)) {
+        if self.extra_carets.is_empty() {
+            edit(self);
+            return;
+        }
+        let primary = self.text_location;
+        let mut buffer = self.buffer.borrow_mut();
+        if typing {
+            buffer.checkpoint_typing(primary);
+        } else {
+            buffer.checkpoint(primary);
+        }
+        drop(buffer);
+        self.batch_edit = true;
+        let mut carets: Vec<Location> = self.extra_carets.drain(..).chain([primary]).collect();
+        carets.sort_unstable();
+        carets.dedup();
+        let mut edited: Vec<(bool, Location)> = Vec::with_capacity(carets.len());
+        for caret in carets.into_iter().rev() {
+            let height = self.buffer.borrow().height();
+            let tails: Vec<usize> = edited.iter().map(|(_, location)| self.distance_to_line_end(*location)).collect();
+            self.text_location = caret;
+            self.selection = None;
+            edit(self);
+            let new_height = self.buffer.borrow().height();
+            for ((_, location), tail) in edited.iter_mut().zip(tails) {
+                // 编辑拆分或合并行时，之后的光标随行数的变化移动
+                let line_index = location.line_index.saturating_add(new_height).saturating_sub(height);
+                let line_length = self.buffer.borrow().lines.get(line_index).map_or(0, Line::grapheme_count);
+                *location = Location { grapheme_index: line_length.saturating_sub(tail), line_index };
+            }
+            edited.push((caret == primary, self.text_location));
+        }
+        self.batch_edit = false;
+        self.text_location = edited
+            .iter()
+            .find(|(is_primary, _)| *is_primary)
+            .map_or(self.text_location, |(_, location)| *location);
+        self.set_extra_carets(edited.into_iter().map(|(_, location)| location).collect());
+        if typing {
+            self.buffer.borrow_mut().continue_typing(self.text_location);
+        }
+        self.scroll_text_location_into_view();
+        self.needs_redraw = true;
+    }
+
+    /// 按方向移动所有光标，最后移动主光标以便滚动跟随主光标
+    pub(super) fn move_carets(&mut self, direction: Direction) {
+        let primary = self.text_location;
+        let extras = std::mem::take(&mut self.extra_carets);
+        let mut moved = Vec::with_capacity(extras.len());
+        for caret in extras {
+            self.text_location = caret;
+            self.move_text_location(direction);
+            moved.push(self.text_location);
+        }
+        self.text_location = primary;
+        self.move_text_location(direction);
+        self.set_extra_carets(moved);
+        self.needs_redraw = true;
+    }
+
+    /// 在最上方光标的上一行或最下方光标的下一行添加光标，保持主光标的显示列
+    pub(super) fn add_caret_vertically(&mut self, down: bool) {
+        let buffer = self.buffer.borrow();
+        let all = self.extra_carets.iter().chain([&self.text_location]);
+        let edge = if down { all.max() } else { all.min() }.copied().unwrap_or(self.text_location);
+        let col = buffer
+            .lines
+            .get(self.text_location.line_index)
+            .map_or(0, |line| line.width_until(self.text_location.grapheme_index));
+        let line_index = if down {
+            edge.line_index.saturating_add(1)
+        } else {
+            match edge.line_index.checked_sub(1) {
+                Some(line_index) => line_index,
+                None => return,
+            }
+        };
+        let Some(line) = buffer.lines.get(line_index) else {
+            return;
+        };
+        let caret = Location { grapheme_index: line.grapheme_index_at(col), line_index };
+        drop(buffer);
+        let mut carets = std::mem::take(&mut self.extra_carets);
+        carets.push(caret);
+        self.set_extra_carets(carets);
+        self.needs_redraw = true;
+    }
+
+    /// 在选中文本或光标处单词的下一处出现的末尾添加光标，主光标移动到自身所在处的末尾
+    /// 没有新的出现位置时返回false
+    pub fn add_next_occurrence(&mut self) -> bool {
+        let Some((query, start)) = self.occurrence_query() else {
+            return false;
+        };
+        let query_length = Line::from(&query).grapheme_count();
+        if self.extra_carets.is_empty() {
+            self.text_location = Location { grapheme_index: start.grapheme_index.saturating_add(query_length), ..start };
+        }
+        self.selection = None;
+        let buffer = self.buffer.borrow();
+        let mut from = self.extra_carets.iter().chain([&self.text_location]).max().copied().unwrap_or(start);
+        // 到达文件末尾后从头继续查找，跳过已经有光标的出现位置
+        for _ in 0..=self.extra_carets.len() {
+            let Some(found) = buffer.search_forward(&query, from) else {
+                break;
+            };
+            let end = Location { grapheme_index: found.grapheme_index.saturating_add(query_length), ..found };
+            if end != self.text_location && !self.extra_carets.contains(&end) {
+                drop(buffer);
+                self.extra_carets.push(end);
+                self.needs_redraw = true;
+                return true;
+            }
+            from = Location { grapheme_index: found.grapheme_index.saturating_add(1), ..found };
+        }
+        self.needs_redraw = true;
+        false
+    }
+
+    /// 查找的文本及其开始位置，为单行的选中文本或者光标处的单词
+    fn occurrence_query(&self) -> Option<(String, Location)> {
+        if let (Some((text, false)), Some((start, _))) = (self.selected_text(), self.selection_range()) {
+            if !text.is_empty() && !text.contains('\n') {
+                return Some((text, start));
+            }
+        }
+        let Location { grapheme_index, line_index } = self.text_location;
+        let buffer = self.buffer.borrow();
+        let line = buffer.lines.get(line_index)?;
+        let range = line
+            .word_ranges()
+            .into_iter()
+            .find(|range| range.start <= grapheme_index && grapheme_index <= range.end)?;
+        let start = Location { grapheme_index: range.start, line_index };
+        Some((line.substring(range), start))
+    }
+
+    /// 在view中指定的terminal位置添加光标，该位置已有光标时移除该光标
+    pub(super) fn toggle_caret_at(&mut self, position: Position) {
+        let Some(location) = self.location_at(position) else {
+            return;
+        };
+        if let Some(index) = self.extra_carets.iter().position(|caret| *caret == location) {
+            self.extra_carets.remove(index);
+        } else if location != self.text_location {
+            self.extra_carets.push(location);
+        }
+        self.needs_redraw = true;
+    }
+
+    /// 移除所有额外的光标
+    pub fn clear_carets(&mut self) {
+        if !self.extra_carets.is_empty() {
+            self.extra_carets.clear();
+            self.needs_redraw = true;
+        }
+    }
+
+    /// 文本被修改后（例如保存时删除行末空白或在其他窗格中编辑）将额外的光标移动到合法的位置
+    pub(super) fn snap_extra_carets(&mut self) {
+        let buffer = self.buffer.borrow();
+        let carets: Vec<Location> = self.extra_carets.iter().map(|caret| Self::clamp_location(&buffer, *caret)).collect();
+        drop(buffer);
+        self.set_extra_carets(carets);
+    }
+
+    /// 设置额外的光标，去掉重复的以及与主光标重合的光标
+    fn set_extra_carets(&mut self, mut carets: Vec<Location>) {
+        carets.sort_unstable();
+        carets.dedup();
+        carets.retain(|caret| *caret != self.text_location);
+        self.extra_carets = carets;
+    }
+
+    /// 位置到所在行行末的字素个数
+    fn distance_to_line_end(&self, location: Location) -> usize {
+        let buffer = self.buffer.borrow();
+        let line_length = buffer.lines.get(location.line_index).map_or(0, Line::grapheme_count);
+        line_length.saturating_sub(location.grapheme_index)
+    }
+
+    /// terminal中的位置对应的文本位置，位置不在view中时返回None
+    /// 位置在行末之后时对应行末，在最后一行之后时对应最后一行
+    fn location_at(&self, position: Position) -> Option<Location> {
+        let row = position.row.checked_sub(self.origin.row).filter(|row| *row < self.size.height)?;
+        let col = position.col.checked_sub(self.origin.col).filter(|col| *col < self.size.width)?;
+        let col = col.saturating_sub(self.gutter_width());
+        let buffer = self.buffer.borrow();
+        let last_line = buffer.height().saturating_sub(1);
+        let (line_index, col) = if buffer.settings.soft_wrap {
+            let text_width = self.text_width();
+            let mut remaining = row;
+            let mut line_index = self.scroll_offset.row;
+            loop {
+                let Some(line) = buffer.lines.get(line_index) else {
+                    break (last_line, usize::MAX);
+                };
+                let row_starts = line.wrap(text_width);
+                if let Some(start) = row_starts.get(remaining) {
+                    // 折行的显示行中，行末之后的位置对应下一显示行开头之前
+                    let row_end = row_starts
+                        .get(remaining.saturating_add(1))
+                        .map_or(usize::MAX, |next| line.width_until(*next).saturating_sub(1));
+                    break (line_index, line.width_until(*start).saturating_add(col).min(row_end));
+                }
+                remaining = remaining.saturating_sub(row_starts.len());
+                line_index = line_index.saturating_add(1);
+            }
+        } else {
+            let line_index = self.scroll_offset.row.saturating_add(row);
+            if line_index > last_line {
+                (last_line, usize::MAX)
+            } else {
+                (line_index, self.scroll_offset.col.saturating_add(col))
+            }
+        };
+        let grapheme_index = buffer.lines.get(line_index).map_or(0, |line| line.grapheme_index_at(col));
+        Some(Location { grapheme_index, line_index })
+    }
+}
+
+#[test]
+fn test_edit_at_carets() {
+    use super::super::editorcommand::EditorCommand;
+    let mut view = View::default();
+    view.buffer.borrow_mut().insert_text(Location::default(), "ab ab\nab");
+    view.text_location = Location { grapheme_index: 1, line_index: 0 };
+    assert!(view.add_next_occurrence());
+    assert!(view.add_next_occurrence());
+    assert!(!view.add_next_occurrence());
+    assert_eq!(view.text_location, Location { grapheme_index: 2, line_index: 0 });
+    view.handle_command(EditorCommand::Insert('c'));
+    view.handle_command(EditorCommand::Insert('d'));
+    view.handle_command(EditorCommand::InsertNewline);
+    assert_eq!(view.buffer.borrow().text(), vec!["abcd", " abcd", "", "abcd", ""]);
+    assert_eq!(view.text_location, Location { grapheme_index: 0, line_index: 1 });
+    assert_eq!(view.extra_carets, vec![Location { grapheme_index: 0, line_index: 2 }, Location { grapheme_index: 0, line_index: 4 }]);
+    view.handle_command(EditorCommand::Backspace);
+    view.handle_command(EditorCommand::Undo);
+    assert_eq!(view.buffer.borrow().text(), vec!["abcd", " abcd", "", "abcd", ""]);
+    view.handle_command(EditorCommand::Undo);
+    assert_eq!(view.buffer.borrow().text(), vec!["abcd abcd", "abcd"]);
+    view.handle_command(EditorCommand::Undo);
+    assert_eq!(view.buffer.borrow().text(), vec!["ab ab", "ab"]);
+    assert!(view.extra_carets.is_empty());
+
+    // 切换注释作用于所有光标所在的行，作为一步撤销
+    view.buffer.borrow_mut().file_type = super::super::filetype::FileType::Python;
+    view.text_location = Location { grapheme_index: 1, line_index: 0 };
+    view.extra_carets = vec![Location { grapheme_index: 2, line_index: 1 }];
+    assert!(view.toggle_comment());
+    assert_eq!(view.buffer.borrow().text(), vec!["# ab ab", "# ab"]);
+    assert_eq!(view.extra_carets, vec![Location { grapheme_index: 4, line_index: 1 }]);
+    view.handle_command(EditorCommand::Undo);
+    assert_eq!(view.buffer.borrow().text(), vec!["ab ab", "ab"]);
+}
+
+#[test]
+fn test_newline_at_carets_on_different_lines() {
+    use super::super::editorcommand::EditorCommand;
+    let mut view = View::default();
+    view.buffer.borrow_mut().insert_text(Location::default(), "ab\ncd\nef");
+    view.text_location = Location { grapheme_index: 1, line_index: 0 };
+    view.extra_carets = vec![Location { grapheme_index: 1, line_index: 1 }, Location { grapheme_index: 2, line_index: 2 }];
+    view.handle_command(EditorCommand::InsertNewline);
+    assert_eq!(view.buffer.borrow().text(), vec!["a", "b", "c", "d", "ef", ""]);
+    assert_eq!(view.text_location, Location { grapheme_index: 0, line_index: 1 });
+    assert_eq!(view.extra_carets, vec![Location { grapheme_index: 0, line_index: 3 }, Location { grapheme_index: 0, line_index: 5 }]);
+    // 在行末删除合并下一行时，之后行上的光标随之上移
+    view.handle_command(EditorCommand::Backspace);
+    assert_eq!(view.buffer.borrow().text(), vec!["ab", "cd", "ef"]);
+    assert_eq!(view.text_location, Location { grapheme_index: 1, line_index: 0 });
+    assert_eq!(view.extra_carets, vec![Location { grapheme_index: 1, line_index: 1 }, Location { grapheme_index: 2, line_index: 2 }]);
+}
@@ -0,0 +1,128 @@
+use super::Location;
+
+/// 最多保存的撤销步数
+const MAX_REVISIONS: usize = 200;
+
+/// 对连续若干行的一次修改：从`line_index`开始的`removed`各行被替换为`inserted`各行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub line_index: usize, // 修改的第一行
+    pub removed: Vec<String>, // 修改之前的各行
+    pub inserted: Vec<String>, // 修改之后的各行
+}
+
+/// 作为一步撤销或重做的一组修改
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Revision {
+    pub changes: Vec<Change>, // 按发生顺序排列的修改
+    pub caret: Location, // 修改之前的光标位置，撤销后光标回到该位置
+    pub redo_caret: Location, // 撤销时的光标位置，重做后光标回到该位置
+}
+
+/// buffer的修改历史，用于撤销和重做
+#[derive(Debug, Default)]
+pub struct History {
+    undo: Vec<Revision>, // 可以撤销的修改，最近的在最后
+    redo: Vec<Revision>, // 撤销后可以重做的修改，最近撤销的在最后
+    open: bool, // 最近的一步是否仍在记录修改，撤销或重做之后的修改需要开始新的一步
+    typing_at: Option<Location>, // 连续输入字符时上一个字符之后的位置，在该位置继续输入时合并为一步
+}
+
+impl History {
+    /// 开始新的一步，之后记录的修改作为一步撤销；上一步没有修改时直接沿用
+    pub fn checkpoint(&mut self, caret: Location) {
+        self.typing_at = None;
+        if let Some(last) = self.undo.last_mut().filter(|last| self.open && last.changes.is_empty()) {
+            last.caret = caret;
+            last.redo_caret = caret;
+            return;
+        }
+        self.undo.push(Revision { changes: Vec::new(), caret, redo_caret: caret });
+        if self.undo.len() > MAX_REVISIONS {
+            self.undo.remove(0);
+        }
+        self.open = true;
+    }
+
+    /// 在caret处输入字符之前调用，紧接着上一次输入继续输入时与上一次合并为一步
+    pub fn checkpoint_typing(&mut self, caret: Location) {
+        if !self.open || self.typing_at != Some(caret) {
+            self.checkpoint(caret);
+        }
+    }
+
+    /// 记录输入字符之后的光标位置
+    pub fn continue_typing(&mut self, caret: Location) {
+        self.typing_at = Some(caret);
+    }
+
+    /// 将一次修改加入最近的一步，新的修改使之前撤销的修改无法重做
+    /// 紧接着修改同一段行的修改与上一次合并，连续输入时只保留该行最初和最新的内容
+    pub fn record(&mut self, change: Change) {
+        if !self.open {
+            self.checkpoint(Location { grapheme_index: 0, line_index: change.line_index });
+        }
+        self.redo.clear();
+        let Some(revision) = self.undo.last_mut() else {
+            return;
+        };
+        match revision.changes.last_mut() {
+            Some(last) if last.line_index == change.line_index && last.inserted == change.removed => {
+                last.inserted = change.inserted;
+            }
+            _ => revision.changes.push(change),
+        }
+    }
+
+    /// 取出最近一步有修改的撤销记录并移入重做记录，caret为撤销时的光标位置
+    pub fn undo(&mut self, caret: Location) -> Option<&Revision> {
+        self.open = false;
+        self.typing_at = None;
+        let mut revision = loop {
+            let revision = self.undo.pop()?;
+            if !revision.changes.is_empty() {
+                break revision;
+            }
+        };
+        revision.redo_caret = caret;
+        self.redo.push(revision);
+        self.redo.last()
+    }
+
+    /// 取出最近一次撤销的记录并移回撤销记录
+    pub fn redo(&mut self) -> Option<&Revision> {
+        self.open = false;
+        self.typing_at = None;
+        let revision = self.redo.pop()?;
+        self.undo.push(revision);
+        self.undo.last()
+    }
+}
+
+#[test]
+fn test_history() {
+    let at = |grapheme_index| Location { grapheme_index, line_index: 0 };
+    let change = |removed: &str, inserted: &str| Change {
+        line_index: 0,
+        removed: vec![removed.to_string()],
+        inserted: vec![inserted.to_string()],
+    };
+    let mut history = History::default();
+    history.checkpoint_typing(at(0));
+    history.record(change("", "a"));
+    history.continue_typing(at(1));
+    history.checkpoint_typing(at(1));
+    history.record(change("a", "ab"));
+    history.checkpoint(at(2));
+    history.checkpoint(at(2));
+    history.record(change("ab", "abc"));
+    // 连续输入合并为一个修改，没有修改的一步被跳过
+    let undone = history.undo(at(3)).cloned();
+    assert_eq!(undone.map(|revision| (revision.changes, revision.caret)), Some((vec![change("ab", "abc")], at(2))));
+    let undone = history.undo(at(2)).cloned();
+    assert_eq!(undone.map(|revision| (revision.changes, revision.caret)), Some((vec![change("", "ab")], at(0))));
+    assert!(history.undo(at(0)).is_none());
+    assert_eq!(history.redo().map(|revision| revision.redo_caret), Some(at(2)));
+    history.record(change("ab", "abd"));
+    assert!(history.redo().is_none());
+}
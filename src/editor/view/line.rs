@@ -1,19 +1,32 @@
 use std::{fmt, ops::Range};
+use unicode_bidi::{Level, ParagraphBidiInfo};
 use unicode_segmentation::UnicodeSegmentation;
-use unicode_width::UnicodeWidthStr;
+
+use super::super::{
+    annotation::{Annotation, AnnotationType},
+    config::DEFAULT_TAB_WIDTH,
+    width::WidthModel,
+};
 
 #[derive(Clone, Copy, Debug)]
 enum GraphemeWidth {
     Half,
     Full,
+    Wide(usize), // 超过两列的字素，例如不支持合并显示ZWJ序列的终端中逐个显示的emoji
+    Tab, // 制表符，宽度取决于所在列，延伸到下一个制表位
 }
 
 impl GraphemeWidth {
-    /// 根据view显示宽度进行增加
-    const fn saturating_add(self, other: usize) -> usize{
+    /// 根据view显示宽度进行增加，other为字素起始列
+    const fn saturating_add(self, other: usize, tab_width: usize) -> usize{
         match self {
             Self::Half => other.saturating_add(1),
             Self::Full => other.saturating_add(2),
+            Self::Wide(width) => other.saturating_add(width),
+            Self::Tab => match other.checked_rem(tab_width) {
+                Some(rem) => other.saturating_add(tab_width.saturating_sub(rem)),
+                None => other.saturating_add(1),
+            },
         }
     }
 }
@@ -26,38 +39,119 @@ struct TextFragment {
     replacement: Option<char>, // 将宽度为0的替换
 }
 
+/// 按显示顺序排列的一个字素及其占据的显示列
+struct Cell<'a> {
+    index: usize, // 字素在逻辑顺序中的下标
+    fragment: &'a TextFragment,
+    rtl: bool, // 是否位于从右向左的文本中，括号需要镜像显示
+    cols: Range<usize>, // 占据的显示列
+}
+
 #[derive(Debug)]
 pub struct Line {
     fragments: Vec<TextFragment>,
+    tab_width: usize, // 制表符宽度
+    visual_order: Option<Vec<(usize, bool)>>, // 含有从右向左的文本时，每个显示位置的字素下标以及是否从右向左；None表示显示顺序与逻辑顺序相同
 }
 
 impl Line {
-    /// 将String转换为Line
+    /// 将`String`转换为`Line`
     pub fn from(line_str: &str) -> Self {
         let fragments = Self::str_to_fragments(line_str);
         Self {
+            visual_order: Self::bidi_order(&fragments),
             fragments,
+            tab_width: DEFAULT_TAB_WIDTH,
+        }
+    }
+
+    /// 修改字素后重新计算显示顺序
+    fn set_fragments(&mut self, fragments: Vec<TextFragment>) {
+        self.visual_order = Self::bidi_order(&fragments);
+        self.fragments = fragments;
+    }
+
+    /// 按照Unicode双向算法计算一行的显示顺序，整行作为一个段落，方向由第一个强方向字符决定
+    /// 从右向左的文本中字素的顺序颠倒，不含从右向左的文本时返回None
+    fn bidi_order(fragments: &[TextFragment]) -> Option<Vec<(usize, bool)>> {
+        let text: String = fragments.iter().map(|fragment| fragment.grapheme.as_str()).collect();
+        // 从右向左的字符和方向控制字符都不小于U+0590，大部分行可以跳过双向算法
+        if text.chars().all(|ch| ch < '\u{590}') {
+            return None;
+        }
+        let info = ParagraphBidiInfo::new(&text, None);
+        if !info.has_rtl() {
+            return None;
+        }
+        let mut grapheme_starts = Vec::with_capacity(fragments.len());
+        let mut byte_index: usize = 0;
+        for fragment in fragments {
+            grapheme_starts.push(byte_index);
+            byte_index = byte_index.saturating_add(fragment.grapheme.len());
         }
+        let (levels, runs) = info.visual_runs(0..text.len());
+        let mut order = Vec::with_capacity(fragments.len());
+        // 每个方向一致的片段按显示顺序排列，从右向左的片段内部颠倒顺序
+        for run in runs {
+            let rtl = levels.get(run.start).is_some_and(Level::is_rtl);
+            let first = grapheme_starts.partition_point(|start| *start < run.start);
+            let last = grapheme_starts.partition_point(|start| *start < run.end);
+            if rtl {
+                order.extend((first..last).rev().map(|index| (index, true)));
+            } else {
+                order.extend((first..last).map(|index| (index, false)));
+            }
+        }
+        Some(order)
+    }
+
+    /// 按显示顺序依次返回每个字素及其占据的显示列，制表符按照所在的显示列展开
+    fn cells(&self) -> impl Iterator<Item = Cell<'_>> {
+        (0..self.fragments.len())
+            .map(|slot| match &self.visual_order {
+                Some(order) => order.get(slot).copied().unwrap_or((slot, false)),
+                None => (slot, false),
+            })
+            .filter_map(|(index, rtl)| Some((index, rtl, self.fragments.get(index)?)))
+            .scan(0, |col, (index, rtl, fragment)| {
+                let start = *col;
+                *col = fragment.rendered_width.saturating_add(start, self.tab_width);
+                Some(Cell { index, fragment, rtl, cols: start..*col })
+            })
+    }
+
+    /// 设置制表符宽度
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        self.tab_width = tab_width;
     }
 
     /// 将字符串转换为 Vec<TextFragment>
     fn str_to_fragments(line_str: &str) -> Vec<TextFragment> {
+        let model = WidthModel::current();
         // 将line_str转为字素数组
         line_str
             .graphemes(true)
             .map(|grapheme| {
-                let (replacement, rendered_width) = Self::replacement_character(grapheme)
+                // 宽度模型给出的字素宽度，emoji序列按照终端的显示方式作为一个整体计算
+                let width = model.grapheme_width(grapheme);
+                let (replacement, rendered_width) = Self::replacement_character(grapheme, width)
                 .map_or_else(
                     || {
-                        // unicode_width提供的grapheme宽度函数
-                        let unicode_width = grapheme.width();
-                        let rendered_width = match unicode_width {
+                        let rendered_width = match width {
                             0 | 1 => GraphemeWidth::Half,
-                            _ => GraphemeWidth::Full,
+                            2 => GraphemeWidth::Full,
+                            _ => GraphemeWidth::Wide(width),
                         };
                         (None, rendered_width)
                     }, 
-                    |replacement| (Some(replacement), GraphemeWidth::Half)
+                    |replacement| {
+                        let rendered_width = if grapheme == "\t" {
+                            GraphemeWidth::Tab
+                        } else {
+                            GraphemeWidth::Half
+                        };
+                        (Some(replacement), rendered_width)
+                    }
                 );
                 TextFragment {
                     grapheme: grapheme.to_string(),
@@ -67,9 +161,8 @@ impl Line {
             }).collect()
     }
 
-    /// 将特殊字符进行替换
-    fn replacement_character(for_str: &str) -> Option<char> {
-        let width = for_str.width();
+    /// 将特殊字符进行替换，width为字素的显示宽度，宽度为0的字素替换为可见的字符
+    fn replacement_character(for_str: &str, width: usize) -> Option<char> {
         match for_str {
             " " => None,
             "\t" => Some(' '),
@@ -87,47 +180,259 @@ impl Line {
         }
     }
 
+    /// 显示空白时替换空白字符的符号，不是空白字符时返回None
+    /// 不换行空格和零宽空格使用不同的符号，以便与普通空格区分
+    fn whitespace_symbol(grapheme: &str) -> Option<char> {
+        match grapheme {
+            "\t" => Some('→'),
+            "\u{a0}" | "\u{2007}" | "\u{202f}" => Some('⍽'),
+            "\u{200b}" | "\u{200c}" | "\u{200d}" | "\u{2060}" | "\u{feff}" => Some('¦'),
+            _ if grapheme.chars().all(char::is_whitespace) => Some('·'),
+            _ => None,
+        }
+    }
+
     /// 获取显示在view上的字素
     pub fn get_visible_graphemes(&self, range: Range<usize>) -> String {
+        self.visible_graphemes(range, false)
+    }
+
+    /// 获取显示在view上的字素，`show_whitespace`为true时空白字符显示为对应的符号
+    fn visible_graphemes(&self, range: Range<usize>, show_whitespace: bool) -> String {
         if range.start >= range.end {
             return String::new();
         }
         let mut result = String::new();
-        let mut current_pos = 0;
-        for fragment in &self.fragments {
-            let fragment_end = fragment.rendered_width.saturating_add(current_pos);
+        for Cell { fragment, rtl, cols, .. } in self.cells() {
+            let (current_pos, fragment_end) = (cols.start, cols.end);
             if current_pos >= range.end {
                 break;
             }
             if fragment_end > range.start {
-                // 边缘字素显示处理，full字素不能完全显示在视图上（占两格宽，只能显示一半），用~替换
-                if fragment_end > range.end || current_pos < range.start {
-                    result.push('~');
+                if matches!(fragment.rendered_width, GraphemeWidth::Tab) {
+                    // 制表符展开为空格，只显示在范围内的部分；显示空白时第一列显示为箭头
+                    let mut visible = fragment_end.min(range.end).saturating_sub(current_pos.max(range.start));
+                    if show_whitespace && current_pos >= range.start {
+                        result.push('→');
+                        visible = visible.saturating_sub(1);
+                    }
+                    result.push_str(&" ".repeat(visible));
+                } else if fragment_end > range.end || current_pos < range.start {
+                    // 边缘字素显示处理，宽字素不能完全显示在视图上（例如占两格宽，只能显示一半），可见的每一列用~替换
+                    let visible = fragment_end.min(range.end).saturating_sub(current_pos.max(range.start));
+                    result.push_str(&"~".repeat(visible));
+                } else if let Some(symbol) = Self::whitespace_symbol(&fragment.grapheme).filter(|_| show_whitespace) {
+                    result.push(symbol);
                 } else if let Some(ch) = fragment.replacement {
                     result.push(ch);
+                } else if let Some(mirrored) = Self::mirrored(&fragment.grapheme).filter(|_| rtl) {
+                    // 从右向左的文本中括号显示为镜像的字符，保持视觉上的开闭方向
+                    result.push(mirrored);
                 } else {
                     result.push_str(&fragment.grapheme);
                 }
             }
-            current_pos = fragment_end;
         }
         result
     }
 
+    /// 成对字符的镜像字符
+    fn mirrored(grapheme: &str) -> Option<char> {
+        match grapheme {
+            "(" => Some(')'),
+            ")" => Some('('),
+            "[" => Some(']'),
+            "]" => Some('['),
+            "{" => Some('}'),
+            "}" => Some('{'),
+            "<" => Some('>'),
+            ">" => Some('<'),
+            _ => None,
+        }
+    }
+
+    /// 显示空白时空白字符所在的显示列，行末的空白标记为需要警告的样式
+    pub fn whitespace_annotations(&self) -> Vec<Annotation> {
+        let trailing_start = self
+            .fragments
+            .iter()
+            .rposition(|fragment| Self::whitespace_symbol(&fragment.grapheme).is_none())
+            .map_or(0, |index| index.saturating_add(1));
+        let mut annotations: Vec<Annotation> = Vec::new();
+        for Cell { index, fragment, cols, .. } in self.cells() {
+            if Self::whitespace_symbol(&fragment.grapheme).is_some() {
+                let annotation_type =
+                    if index >= trailing_start { AnnotationType::TrailingWhitespace } else { AnnotationType::Whitespace };
+                // 相邻的同类空白合并为一个注释
+                match annotations.last_mut() {
+                    Some(last) if last.range.end == cols.start && last.annotation_type == annotation_type => {
+                        last.range.end = cols.end;
+                    }
+                    _ => annotations.push(Annotation { range: cols, annotation_type }),
+                }
+            }
+        }
+        annotations
+    }
+
+    /// 获取显示在view上的字素，并按照注释拆分为多个片段，重叠的注释以后面的为准
+    /// 注释超出行末的部分用空格显示，例如选中的换行符
+    pub fn get_annotated_graphemes(
+        &self,
+        range: Range<usize>,
+        annotations: &[Annotation],
+        show_whitespace: bool,
+    ) -> Vec<(String, Option<AnnotationType>)> {
+        let mut boundaries = vec![range.start, range.end];
+        for annotation in annotations {
+            for boundary in [annotation.range.start, annotation.range.end] {
+                if range.contains(&boundary) {
+                    boundaries.push(boundary);
+                }
+            }
+        }
+        boundaries.sort_unstable();
+        boundaries.dedup();
+        let width = self.width();
+        let mut pieces = Vec::new();
+        for window in boundaries.windows(2) {
+            let &[start, end] = window else {
+                continue;
+            };
+            let annotation_type = annotations
+                .iter()
+                .rev()
+                .find(|annotation| annotation.range.start <= start && start < annotation.range.end)
+                .map(|annotation| annotation.annotation_type);
+            let mut text = self.visible_graphemes(start..end.min(width), show_whitespace);
+            if annotation_type.is_some() && end > width {
+                text.push_str(&" ".repeat(end.saturating_sub(start.max(width))));
+            }
+            if !text.is_empty() {
+                pieces.push((text, annotation_type));
+            }
+        }
+        pieces
+    }
+
+    /// 获取指定字素范围内的文本
+    pub fn substring(&self, range: Range<usize>) -> String {
+        self.fragments
+            .iter()
+            .skip(range.start)
+            .take(range.end.saturating_sub(range.start))
+            .map(|fragment| fragment.grapheme.as_str())
+            .collect()
+    }
+
+    /// 按照显示宽度折行，返回每一显示行最左侧字素的下标，含有从右向左的文本时按显示顺序折行
+    /// 字素不会被拆分到两行，宽度不足以容纳单个字素时该字素独占一行
+    pub fn wrap(&self, width: usize) -> Vec<usize> {
+        let mut cells = self.cells();
+        let mut row_starts = vec![cells.next().map_or(0, |cell| cell.index)];
+        let mut row_start_col = 0;
+        for Cell { index, cols, .. } in cells {
+            if cols.end.saturating_sub(row_start_col) > width && cols.start > row_start_col {
+                row_starts.push(index);
+                row_start_col = cols.start;
+            }
+        }
+        row_starts
+    }
+
+    /// 获取指定位置的字素
+    pub fn grapheme(&self, grapheme_index: usize) -> Option<&str> {
+        self.fragments.get(grapheme_index).map(|fragment| fragment.grapheme.as_str())
+    }
+
+    /// 每个字素是否为代码，即不在字符串和行注释中
+    /// 只识别单行内的字符串，字符串中反斜杠转义下一个字素
+    pub fn code_graphemes(&self, line_comment: Option<&str>, quotes: &[char]) -> Vec<bool> {
+        let line_str = self.to_string();
+        let mut byte_index: usize = 0;
+        let mut quote: Option<&str> = None;
+        let mut escaped = false;
+        let mut code = Vec::with_capacity(self.fragments.len());
+        for fragment in &self.fragments {
+            let grapheme = fragment.grapheme.as_str();
+            if let Some(open) = quote {
+                code.push(false);
+                if escaped {
+                    escaped = false;
+                } else if grapheme == "\\" {
+                    escaped = true;
+                } else if grapheme == open {
+                    quote = None;
+                }
+            } else if line_comment.is_some_and(|comment| line_str.get(byte_index..).is_some_and(|rest| rest.starts_with(comment))) {
+                code.resize(self.fragments.len(), false);
+                break;
+            } else if grapheme.chars().count() == 1 && grapheme.chars().all(|ch| quotes.contains(&ch)) {
+                code.push(false);
+                quote = Some(grapheme);
+            } else {
+                code.push(true);
+            }
+            byte_index = byte_index.saturating_add(grapheme.len());
+        }
+        code
+    }
+
+    /// 是否为空行或只包含空白的行
+    pub fn is_blank(&self) -> bool {
+        self.fragments.iter().all(|fragment| fragment.grapheme.chars().all(char::is_whitespace))
+    }
+
     /// 获取Line中字素个数
     pub fn grapheme_count(&self) -> usize {
         self.fragments.len()
     }
 
-    /// 获取line中起始位置到当前字素的宽度
+    /// 指定字素显示的起始列，即光标位于该字素时显示的列；位于行末时为整行宽度
+    /// 不含从右向左的文本时即为起始位置到该字素的宽度
     pub fn width_until(&self, grapheme_index: usize) -> usize {
-        self.fragments.iter().take(grapheme_index).map(|grapheme| {
-            match grapheme.rendered_width {
-                GraphemeWidth::Half => 1,
-                GraphemeWidth::Full => 2,
+        let mut width = 0;
+        for cell in self.cells() {
+            if cell.index == grapheme_index {
+                return cell.cols.start;
             }
-        })
-        .sum()
+            width = cell.cols.end;
+        }
+        width
+    }
+
+    /// 指定字素占据的显示列，位于行末时为行末之后的一列
+    pub fn grapheme_cols(&self, grapheme_index: usize) -> Range<usize> {
+        self.cells().find(|cell| cell.index == grapheme_index).map_or_else(
+            || {
+                let width = self.width();
+                width..width.saturating_add(1)
+            },
+            |cell| cell.cols,
+        )
+    }
+
+    /// 字素范围占据的显示列，含有从右向左的文本时可能不连续
+    pub fn cols_of(&self, range: Range<usize>) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for Cell { cols, .. } in self.cells().filter(|cell| range.contains(&cell.index)) {
+            match ranges.last_mut() {
+                Some(last) if last.end == cols.start => last.end = cols.end,
+                _ => ranges.push(cols),
+            }
+        }
+        ranges
+    }
+
+    /// 占据显示列col的字素位置，col超过整行宽度时为行末
+    /// 宽字符跨越col时停在该字符之前，保证不同宽度的字素按显示位置对齐
+    pub fn grapheme_index_at(&self, col: usize) -> usize {
+        self.cells().find(|cell| cell.cols.end > col).map_or(self.grapheme_count(), |cell| cell.index)
+    }
+
+    /// 获取整行显示宽度
+    pub fn width(&self) -> usize {
+        self.cells().last().map_or(0, |cell| cell.cols.end)
     }
 
     /// 在line指定位置中插入字符
@@ -143,12 +448,65 @@ impl Line {
         if grapheme_index >= self.fragments.len() {
             result.push(character);
         }
-        self.fragments = Self::str_to_fragments(&result);
+        self.set_fragments(Self::str_to_fragments(&result));
     }
 
     /// 删除line中指定位置的字符
     pub fn delete(&mut self, grapheme_index: usize) {
         self.fragments.remove(grapheme_index);
+        self.visual_order = Self::bidi_order(&self.fragments);
+    }
+
+    /// 从指定字素开始向后查找query，返回匹配开始处的字素下标
+    pub fn search_forward(&self, query: &str, from_grapheme_index: usize) -> Option<usize> {
+        self.match_starts(query)
+            .into_iter()
+            .find(|grapheme_index| *grapheme_index >= from_grapheme_index)
+    }
+
+    /// 在指定字素之前向前查找query，返回最后一个匹配开始处的字素下标
+    pub fn search_backward(&self, query: &str, before_grapheme_index: usize) -> Option<usize> {
+        self.match_starts(query)
+            .into_iter()
+            .rev()
+            .find(|grapheme_index| *grapheme_index < before_grapheme_index)
+    }
+
+    /// query在行中所有匹配开始处的字素下标
+    /// 只接受从字素边界开始的匹配，避免匹配到组合字符的一部分
+    fn match_starts(&self, query: &str) -> Vec<usize> {
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let mut byte_index: usize = 0;
+        let mut grapheme_starts = Vec::with_capacity(self.fragments.len());
+        for fragment in &self.fragments {
+            grapheme_starts.push(byte_index);
+            byte_index = byte_index.saturating_add(fragment.grapheme.len());
+        }
+        self.to_string()
+            .match_indices(query)
+            .filter_map(|(byte_index, _)| grapheme_starts.binary_search(&byte_index).ok())
+            .collect()
+    }
+
+    /// 按照Unicode单词边界划分的单词的字素范围，不包含空白
+    /// 标识符、标点和CJK字符分别组成单词
+    pub fn word_ranges(&self) -> Vec<Range<usize>> {
+        let mut byte_index: usize = 0;
+        let mut grapheme_starts = Vec::with_capacity(self.fragments.len());
+        for fragment in &self.fragments {
+            grapheme_starts.push(byte_index);
+            byte_index = byte_index.saturating_add(fragment.grapheme.len());
+        }
+        let to_grapheme_index = |byte_index: usize| {
+            grapheme_starts.binary_search(&byte_index).unwrap_or_else(|index| index)
+        };
+        self.to_string()
+            .split_word_bound_indices()
+            .filter(|(_, word)| !word.chars().all(char::is_whitespace))
+            .map(|(start, word)| to_grapheme_index(start)..to_grapheme_index(start.saturating_add(word.len())))
+            .collect()
     }
 
     /// 将另一个line添加当当前line后
@@ -157,7 +515,13 @@ impl Line {
     pub fn append(&mut self, other: &Self) {
         let mut concat = self.to_string();
         concat.push_str(&other.to_string());
-        self.fragments = Self::str_to_fragments(&concat);
+        self.set_fragments(Self::str_to_fragments(&concat));
+    }
+}
+
+impl Default for Line {
+    fn default() -> Self {
+        Self::from("")
     }
 }
 
@@ -173,6 +537,49 @@ impl fmt::Display for Line {
     }
 }
 
+#[test]
+fn test_grapheme_index_at() {
+    let line = Line::from("a中b");
+    assert_eq!(line.grapheme_index_at(0), 0);
+    assert_eq!(line.grapheme_index_at(1), 1);
+    assert_eq!(line.grapheme_index_at(2), 1);
+    assert_eq!(line.grapheme_index_at(3), 2);
+    assert_eq!(line.grapheme_index_at(10), 3);
+}
+
+#[test]
+fn test_whitespace() {
+    let line = Line::from("\ta\u{a0}b \u{200b}");
+    assert_eq!(line.visible_graphemes(0..10, true), "→   a⍽b·¦");
+    assert_eq!(line.visible_graphemes(2..10, true), "  a⍽b·¦");
+    let types: Vec<_> = line.whitespace_annotations().into_iter().map(|annotation| (annotation.range, annotation.annotation_type)).collect();
+    assert_eq!(types, vec![(0..4, AnnotationType::Whitespace), (5..6, AnnotationType::Whitespace), (7..9, AnnotationType::TrailingWhitespace)]);
+}
+
+#[test]
+fn test_bidi() {
+    let line = Line::from("ab שלום cd");
+    assert_eq!(line.get_visible_graphemes(0..10), "ab םולש cd");
+    assert_eq!(line.width_until(3), 6);
+    assert_eq!(line.width_until(7), 7);
+    assert_eq!(line.grapheme_index_at(3), 6);
+    assert_eq!(line.cols_of(2..5), vec![2..3, 5..7]);
+    assert_eq!(line.wrap(4), vec![0, 5, 8]);
+    assert_eq!(Line::from("א(ב)").get_visible_graphemes(0..4), "(ב)א");
+}
+
+#[test]
+fn test_code_graphemes() {
+    let code = Line::from(r#"f("(\")") # x"#).code_graphemes(Some("#"), &['"']);
+    assert_eq!(code, vec![true, true, false, false, false, false, false, false, true, true, false, false, false]);
+}
+
+#[test]
+fn test_word_ranges() {
+    assert_eq!(Line::from("let x_1 = a.b;").word_ranges(), vec![0..3, 4..7, 8..9, 10..13, 13..14]);
+    assert_eq!(Line::from("  中文 e\u{301}té").word_ranges(), vec![2..3, 3..4, 5..8]);
+}
+
 #[test]
 fn test_graphemes() {
     println!("{:?}", Line::from("Control characters:[Escape][Bell]"));
@@ -0,0 +1,385 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::fmt;
+
+use super::editorcommand::{Direction, EditorCommand, SelectionKind};
+
+/// vi的编辑模式
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum Mode {
+    #[default]
+    Normal, // 按键作为命令
+    Insert, // 按键插入文本
+    Visual, // 移动光标扩展选择，操作符作用于选中的文本
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Normal => "NORMAL",
+            Self::Insert => "INSERT",
+            Self::Visual => "VISUAL",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// 操作符，与移动组合使用，作用于光标移动经过的文本
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operator {
+    Delete, // d
+    Change, // c
+    Yank,   // y
+}
+
+impl Operator {
+    /// 操作符对应的按键
+    const fn from_char(ch: char) -> Option<Self> {
+        match ch {
+            'd' => Some(Self::Delete),
+            'c' => Some(Self::Change),
+            'y' => Some(Self::Yank),
+            _ => None,
+        }
+    }
+}
+
+/// 移动
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Motion {
+    Left,         // h
+    Right,        // l
+    Up,           // k
+    Down,         // j
+    LineStart,    // 0
+    LineEnd,      // $
+    WordForward,  // w
+    WordBackward, // b
+    WordEnd,      // e
+    FirstLine,    // gg
+    LastLine,     // G
+    MatchingBracket, // %
+}
+
+impl Motion {
+    /// 与操作符组合时选择的方式
+    const fn selection_kind(self) -> SelectionKind {
+        match self {
+            Self::Up | Self::Down | Self::FirstLine | Self::LastLine => SelectionKind::Lines,
+            Self::WordEnd | Self::MatchingBracket => SelectionKind::Inclusive,
+            Self::Left | Self::Right | Self::LineStart | Self::LineEnd | Self::WordForward | Self::WordBackward => {
+                SelectionKind::Exclusive
+            }
+        }
+    }
+
+    /// 执行count次移动的命令，gg和G的次数为跳转的行号
+    fn commands(self, count: Option<usize>) -> Vec<EditorCommand> {
+        let times = count.unwrap_or(1);
+        let direction = match self {
+            Self::FirstLine => return vec![EditorCommand::JumpToLine(count.unwrap_or(1))],
+            Self::LastLine => return vec![EditorCommand::JumpToLine(count.unwrap_or(usize::MAX))],
+            Self::LineStart => return vec![EditorCommand::Move(Direction::Home)],
+            Self::MatchingBracket => return vec![EditorCommand::Move(Direction::MatchingBracket)],
+            // 带次数的$移动到之后第count-1行的行末
+            Self::LineEnd => {
+                let mut commands = vec![EditorCommand::Move(Direction::Down); times.saturating_sub(1)];
+                commands.push(EditorCommand::Move(Direction::End));
+                return commands;
+            }
+            Self::Left => Direction::Left,
+            Self::Right => Direction::Right,
+            Self::Up => Direction::Up,
+            Self::Down => Direction::Down,
+            Self::WordForward => Direction::NextWordStart,
+            Self::WordBackward => Direction::PrevWordStart,
+            Self::WordEnd => Direction::WordEnd,
+        };
+        vec![EditorCommand::Move(direction); times]
+    }
+}
+
+/// vi按键处理结果
+pub enum ViResult {
+    Commands(Vec<EditorCommand>), // 依次执行的命令
+    Pending, // 命令尚未输入完成，等待后续按键
+    Unhandled, // 不是vi命令，交给按键绑定表处理
+}
+
+/// vi模式编辑层，将按键转换为`EditorCommand`序列
+/// 操作符与移动组合时，先以光标位置为起点开始选择，再移动光标，最后对选中的文本执行操作
+#[derive(Default)]
+pub struct ViMode {
+    mode: Mode, // 当前模式
+    count: Option<usize>, // 正在输入的次数
+    operator: Option<(Operator, Option<usize>)>, // 等待移动的操作符，以及操作符之前输入的次数
+    pending_g: bool, // 已经按下g，等待下一个按键
+}
+
+impl ViMode {
+    /// 当前模式
+    pub const fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// 处理按键，`to_line_end`为光标到行末之间的字素个数
+    pub fn handle_key(&mut self, key: KeyEvent, to_line_end: usize) -> ViResult {
+        if self.mode == Mode::Insert {
+            if key.code == KeyCode::Esc {
+                self.mode = Mode::Normal;
+                return ViResult::Commands(Vec::new());
+            }
+            return ViResult::Unhandled;
+        }
+        // 带有Ctrl或Alt的按键交给按键绑定表，例如保存和退出
+        if key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) {
+            self.reset_pending();
+            return ViResult::Unhandled;
+        }
+        let ch = match key.code {
+            KeyCode::Char(ch) => ch,
+            KeyCode::Left | KeyCode::Backspace => 'h',
+            KeyCode::Right => 'l',
+            KeyCode::Delete => 'x',
+            KeyCode::Up => 'k',
+            KeyCode::Down | KeyCode::Enter => 'j',
+            KeyCode::Home => return self.apply_motion(Motion::LineStart),
+            KeyCode::End => '$',
+            // 缩进只在插入模式中可用，避免在普通模式中修改文本
+            KeyCode::Tab | KeyCode::BackTab => {
+                self.reset_pending();
+                return ViResult::Commands(Vec::new());
+            }
+            KeyCode::Esc => {
+                // 退出可视模式，同时移除多余的光标
+                self.reset_pending();
+                self.mode = Mode::Normal;
+                return ViResult::Commands(vec![EditorCommand::ClearSelection]);
+            }
+            _ => {
+                self.reset_pending();
+                return ViResult::Unhandled;
+            }
+        };
+        self.handle_char(ch, to_line_end)
+    }
+
+    /// 处理普通模式和可视模式中的字符按键
+    fn handle_char(&mut self, ch: char, to_line_end: usize) -> ViResult {
+        if self.pending_g {
+            self.pending_g = false;
+            if ch == 'g' {
+                return self.apply_motion(Motion::FirstLine);
+            }
+            // gc切换当前行或选中各行的注释
+            if ch == 'c' && self.operator.is_none() {
+                self.reset_pending();
+                if self.mode == Mode::Visual {
+                    self.mode = Mode::Normal;
+                    return ViResult::Commands(vec![EditorCommand::ToggleComment, EditorCommand::ClearSelection]);
+                }
+                return ViResult::Commands(vec![EditorCommand::ToggleComment]);
+            }
+            self.reset_pending();
+            return ViResult::Commands(Vec::new());
+        }
+        // 0在输入次数时作为数字，否则为移动到行首
+        if let Some(digit) = ch.to_digit(10) {
+            if digit != 0 || self.count.is_some() {
+                #[allow(clippy::as_conversions)]
+                let digit = digit as usize;
+                self.count = Some(self.count.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+                return ViResult::Pending;
+            }
+        }
+        let motion = match ch {
+            'h' => Some(Motion::Left),
+            'l' | ' ' => Some(Motion::Right),
+            'k' => Some(Motion::Up),
+            'j' => Some(Motion::Down),
+            '0' => Some(Motion::LineStart),
+            '$' => Some(Motion::LineEnd),
+            'w' => Some(Motion::WordForward),
+            'b' => Some(Motion::WordBackward),
+            'e' => Some(Motion::WordEnd),
+            'G' => Some(Motion::LastLine),
+            '%' => Some(Motion::MatchingBracket),
+            'g' => {
+                self.pending_g = true;
+                return ViResult::Pending;
+            }
+            _ => None,
+        };
+        if let Some(motion) = motion {
+            return self.apply_motion(motion);
+        }
+        if let Some(operator) = Operator::from_char(ch) {
+            return self.apply_operator(operator);
+        }
+        let times = self.take_count().unwrap_or(1);
+        self.reset_pending();
+        let commands = match ch {
+            'i' => self.enter_insert(Vec::new()),
+            'a' if to_line_end == 0 => self.enter_insert(Vec::new()),
+            'a' => self.enter_insert(vec![EditorCommand::Move(Direction::Right)]),
+            'I' => self.enter_insert(vec![EditorCommand::Move(Direction::Home)]),
+            'A' => self.enter_insert(vec![EditorCommand::Move(Direction::End)]),
+            'x' if self.mode == Mode::Visual => self.operate(Operator::Delete),
+            // x不跨越行末，空行中不删除任何内容
+            'x' => vec![EditorCommand::Delete; times.min(to_line_end)],
+            'X' => vec![EditorCommand::Backspace; times],
+            'D' => return self.operator_to_line_end(Operator::Delete, times),
+            'C' => return self.operator_to_line_end(Operator::Change, times),
+            'u' => vec![EditorCommand::Undo; times],
+            'p' => vec![EditorCommand::PasteAfter; times],
+            'P' => vec![EditorCommand::Paste; times],
+            'v' if self.mode == Mode::Visual => {
+                self.mode = Mode::Normal;
+                vec![EditorCommand::ClearSelection]
+            }
+            'v' => {
+                self.mode = Mode::Visual;
+                vec![EditorCommand::StartSelection(SelectionKind::Inclusive)]
+            }
+            ':' => vec![EditorCommand::OpenCommandLine],
+            '/' => vec![EditorCommand::Search],
+            'n' => vec![EditorCommand::SearchNext; times],
+            'N' => vec![EditorCommand::SearchPrev; times],
+            _ => Vec::new(),
+        };
+        ViResult::Commands(commands)
+    }
+
+    /// 执行移动，有等待的操作符时对移动经过的文本执行操作
+    fn apply_motion(&mut self, motion: Motion) -> ViResult {
+        let count = self.take_count();
+        let Some((operator, _)) = self.operator.take() else {
+            return ViResult::Commands(motion.commands(count));
+        };
+        // cw与ce相同，不包含单词之后的空白
+        let motion = if operator == Operator::Change && motion == Motion::WordForward {
+            Motion::WordEnd
+        } else {
+            motion
+        };
+        let mut commands = vec![EditorCommand::StartSelection(motion.selection_kind())];
+        let mut motion_commands = motion.commands(count);
+        // 与操作符组合时w的最后一次移动停在行末，例如dw删除行末的单词时不删除换行符
+        if motion == Motion::WordForward {
+            if let Some(last) = motion_commands.last_mut() {
+                *last = EditorCommand::Move(Direction::NextWordStartInLine);
+            }
+        }
+        commands.extend(motion_commands);
+        commands.extend(self.operate(operator));
+        ViResult::Commands(commands)
+    }
+
+    /// 处理操作符按键：可视模式中作用于选中的文本，连续两次相同的操作符作用于整行，否则等待移动
+    fn apply_operator(&mut self, operator: Operator) -> ViResult {
+        if self.mode == Mode::Visual {
+            self.reset_pending();
+            return ViResult::Commands(self.operate(operator));
+        }
+        if let Some((pending, _)) = self.operator {
+            if pending != operator {
+                self.reset_pending();
+                return ViResult::Commands(Vec::new());
+            }
+            let times = self.take_count().unwrap_or(1);
+            self.operator = None;
+            let mut commands = vec![EditorCommand::StartSelection(SelectionKind::Lines)];
+            commands.extend(vec![EditorCommand::Move(Direction::Down); times.saturating_sub(1)]);
+            commands.extend(self.operate(operator));
+            return ViResult::Commands(commands);
+        }
+        self.operator = Some((operator, self.count.take()));
+        ViResult::Pending
+    }
+
+    /// D和C，作用于光标到行末的文本
+    fn operator_to_line_end(&mut self, operator: Operator, times: usize) -> ViResult {
+        self.operator = Some((operator, None));
+        self.count = Some(times);
+        self.apply_motion(Motion::LineEnd)
+    }
+
+    /// 对选中的文本执行操作的命令，c操作之后进入插入模式，其他操作之后回到普通模式
+    fn operate(&mut self, operator: Operator) -> Vec<EditorCommand> {
+        match operator {
+            Operator::Delete => {
+                self.mode = Mode::Normal;
+                vec![EditorCommand::Cut]
+            }
+            Operator::Change => {
+                self.mode = Mode::Insert;
+                vec![EditorCommand::Change]
+            }
+            Operator::Yank => {
+                self.mode = Mode::Normal;
+                vec![EditorCommand::Copy]
+            }
+        }
+    }
+
+    /// 进入插入模式，commands为进入之前执行的命令，从可视模式进入时先取消选择
+    fn enter_insert(&mut self, commands: Vec<EditorCommand>) -> Vec<EditorCommand> {
+        let mut result = Vec::new();
+        if self.mode == Mode::Visual {
+            result.push(EditorCommand::ClearSelection);
+        }
+        result.extend(commands);
+        self.mode = Mode::Insert;
+        result
+    }
+
+    /// 取出输入的次数，操作符前后的次数相乘
+    fn take_count(&mut self) -> Option<usize> {
+        let before = self.operator.and_then(|(_, count)| count);
+        match (before, self.count.take()) {
+            (None, None) => None,
+            (before, after) => Some(before.unwrap_or(1).saturating_mul(after.unwrap_or(1))),
+        }
+    }
+
+    /// 清除等待中的次数、操作符和g
+    fn reset_pending(&mut self) {
+        self.count = None;
+        self.operator = None;
+        self.pending_g = false;
+    }
+}
+
+#[test]
+fn test_operator_with_count() {
+    let key = |ch| KeyEvent::new(KeyCode::Char(ch), KeyModifiers::NONE);
+    let mut vi = ViMode::default();
+    assert!(matches!(vi.handle_key(key('2'), 5), ViResult::Pending));
+    assert!(matches!(vi.handle_key(key('d'), 5), ViResult::Pending));
+    assert!(matches!(vi.handle_key(key('3'), 5), ViResult::Pending));
+    let ViResult::Commands(commands) = vi.handle_key(key('w'), 5) else {
+        panic!("expected commands");
+    };
+    // 开始选择、6次移动、剪切
+    assert_eq!(commands.len(), 8);
+    assert!(matches!(commands.last(), Some(EditorCommand::Cut)));
+
+    assert!(matches!(vi.handle_key(key('c'), 5), ViResult::Pending));
+    let ViResult::Commands(commands) = vi.handle_key(key('c'), 5) else {
+        panic!("expected commands");
+    };
+    assert!(matches!(commands.first(), Some(EditorCommand::StartSelection(SelectionKind::Lines))));
+    assert_eq!(vi.mode(), Mode::Insert);
+
+    // 普通模式中退格为h，Delete为x，Tab被忽略
+    vi.handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE), 5);
+    let special = |code| KeyEvent::new(code, KeyModifiers::NONE);
+    let ViResult::Commands(commands) = vi.handle_key(special(KeyCode::Backspace), 5) else {
+        panic!("expected commands");
+    };
+    assert!(commands == vec![EditorCommand::Move(Direction::Left)]);
+    let ViResult::Commands(commands) = vi.handle_key(special(KeyCode::Delete), 5) else {
+        panic!("expected commands");
+    };
+    assert!(commands == vec![EditorCommand::Delete]);
+    assert!(matches!(vi.handle_key(special(KeyCode::Tab), 5), ViResult::Commands(commands) if commands.is_empty()));
+}
@@ -0,0 +1,191 @@
+use std::sync::OnceLock;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// 探测终端时接受的最大宽度，超过时认为探测失败
+const MAX_PROBED_WIDTH: usize = 16;
+
+/// 启动时确定的宽度模型，所有行共用
+static WIDTH_MODEL: OnceLock<WidthModel> = OnceLock::new();
+
+/// 不同终端显示宽度不一致的emoji序列
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmojiSequence {
+    Presentation, // 以U+FE0F选择emoji样式显示的字符，例如❤️
+    Zwj, // 以U+200D连接的多个emoji，例如👨‍👩‍👧
+    Flag, // 一对区域指示符组成的旗帜，例如🇯🇵
+    RegionalIndicator, // 单独的区域指示符
+    Modifier, // 带有肤色修饰符的emoji，例如👍🏽
+    Keycap, // 键帽序列，例如#️⃣
+}
+
+impl EmojiSequence {
+    /// 所有序列类别，用于逐个探测
+    pub const ALL: [Self; 6] = [
+        Self::Presentation,
+        Self::Zwj,
+        Self::Flag,
+        Self::RegionalIndicator,
+        Self::Modifier,
+        Self::Keycap,
+    ];
+
+    /// 探测终端时打印的样例
+    pub const fn sample(self) -> &'static str {
+        match self {
+            Self::Presentation => "\u{2764}\u{fe0f}",
+            Self::Zwj => "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}",
+            Self::Flag => "\u{1f1ef}\u{1f1f5}",
+            Self::RegionalIndicator => "\u{1f1e6}",
+            Self::Modifier => "\u{1f44d}\u{1f3fd}",
+            Self::Keycap => "#\u{fe0f}\u{20e3}",
+        }
+    }
+
+    /// 字素所属的序列类别，不属于任何类别时返回None
+    pub fn classify(grapheme: &str) -> Option<Self> {
+        let chars: Vec<char> = grapheme.chars().collect();
+        if chars.iter().all(|ch| is_regional_indicator(*ch)) {
+            return match chars.len() {
+                1 => Some(Self::RegionalIndicator),
+                2 => Some(Self::Flag),
+                _ => None,
+            };
+        }
+        if chars.contains(&'\u{20e3}') {
+            return Some(Self::Keycap);
+        }
+        if chars.contains(&'\u{200d}') && chars.iter().filter(|ch| is_pictographic(**ch)).count() >= 2 {
+            return Some(Self::Zwj);
+        }
+        if chars.iter().any(|ch| ('\u{1f3fb}'..='\u{1f3ff}').contains(ch)) {
+            return Some(Self::Modifier);
+        }
+        if chars.contains(&'\u{fe0f}') {
+            return Some(Self::Presentation);
+        }
+        None
+    }
+}
+
+/// 各类emoji序列在终端中占据的列数，默认值与主流的现代终端一致，可以在启动时探测终端实际的显示宽度
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WidthModel {
+    presentation: usize, // 以emoji样式显示的字符
+    zwj: usize, // ZWJ序列，不支持合并显示的终端中为各个emoji宽度之和
+    flag: usize, // 旗帜
+    regional_indicator: usize, // 单独的区域指示符
+    modifier: usize, // 带有肤色修饰符的emoji
+    keycap: usize, // 键帽序列
+}
+
+impl Default for WidthModel {
+    fn default() -> Self {
+        Self {
+            presentation: 2,
+            zwj: 2,
+            flag: 2,
+            regional_indicator: 1,
+            modifier: 2,
+            keycap: 2,
+        }
+    }
+}
+
+impl WidthModel {
+    /// 当前使用的宽度模型，没有设置时使用默认值
+    pub fn current() -> Self {
+        WIDTH_MODEL.get().copied().unwrap_or_default()
+    }
+
+    /// 设置全局使用的宽度模型，需要在创建任何行之前设置，之后的设置被忽略
+    pub fn install(self) {
+        let _ = WIDTH_MODEL.set(self);
+    }
+
+    /// 用`measure`测量每类序列样例的实际显示宽度，测量失败的类别保留默认值
+    pub fn probe(mut measure: impl FnMut(&str) -> Option<usize>) -> Self {
+        let mut model = Self::default();
+        for sequence in EmojiSequence::ALL {
+            if let Some(width) = measure(sequence.sample()).filter(|width| (1..=MAX_PROBED_WIDTH).contains(width)) {
+                *model.width_mut(sequence) = width;
+            }
+        }
+        model
+    }
+
+    /// 一类序列的宽度
+    const fn width(&self, sequence: EmojiSequence) -> usize {
+        match sequence {
+            EmojiSequence::Presentation => self.presentation,
+            EmojiSequence::Zwj => self.zwj,
+            EmojiSequence::Flag => self.flag,
+            EmojiSequence::RegionalIndicator => self.regional_indicator,
+            EmojiSequence::Modifier => self.modifier,
+            EmojiSequence::Keycap => self.keycap,
+        }
+    }
+
+    /// 一类序列的宽度的可变引用
+    fn width_mut(&mut self, sequence: EmojiSequence) -> &mut usize {
+        match sequence {
+            EmojiSequence::Presentation => &mut self.presentation,
+            EmojiSequence::Zwj => &mut self.zwj,
+            EmojiSequence::Flag => &mut self.flag,
+            EmojiSequence::RegionalIndicator => &mut self.regional_indicator,
+            EmojiSequence::Modifier => &mut self.modifier,
+            EmojiSequence::Keycap => &mut self.keycap,
+        }
+    }
+
+    /// 字素的显示宽度，emoji序列使用模型中的宽度，其余字素使用Unicode东亚宽度
+    /// 组合字符等只有零宽字符的字素宽度为0
+    pub fn grapheme_width(&self, grapheme: &str) -> usize {
+        EmojiSequence::classify(grapheme).map_or_else(|| grapheme.width(), |sequence| self.width(sequence))
+    }
+}
+
+/// 是否为区域指示符，两个区域指示符组成一个旗帜
+fn is_regional_indicator(ch: char) -> bool {
+    ('\u{1f1e6}'..='\u{1f1ff}').contains(&ch)
+}
+
+/// 是否为可以组成ZWJ序列的图形符号，近似于Unicode的`Extended_Pictographic`属性
+fn is_pictographic(ch: char) -> bool {
+    matches!(ch, '\u{a9}' | '\u{ae}' | '\u{203c}' | '\u{2049}' | '\u{2122}' | '\u{2139}' | '\u{3030}' | '\u{303d}' | '\u{3297}' | '\u{3299}')
+        || ('\u{2194}'..='\u{21aa}').contains(&ch)
+        || ('\u{2300}'..='\u{23ff}').contains(&ch)
+        || ('\u{25aa}'..='\u{27bf}').contains(&ch)
+        || ('\u{2b00}'..='\u{2bff}').contains(&ch)
+        || ('\u{1f000}'..='\u{1faff}').contains(&ch) && !is_regional_indicator(ch) && ch.width() != Some(0)
+}
+
+/// 常见emoji序列和组合字符的宽度，默认模型下与现代终端的显示一致
+#[test]
+fn test_width_corpus() {
+    let model = WidthModel::default();
+    let corpus = [
+        ("a", 1),
+        ("中", 2),
+        ("e\u{301}", 1),                                   // 组合重音符
+        ("\u{1100}\u{1161}", 2),                           // 谚文字母组成的音节
+        ("\u{301}", 0),                                    // 单独的组合字符
+        ("\u{200b}", 0),                                   // 零宽空格
+        ("\u{2764}", 1),                                   // 默认以文本样式显示
+        ("\u{2764}\u{fe0f}", 2),                           // U+FE0F选择emoji样式
+        ("\u{263a}\u{fe0e}", 1),                           // U+FE0E选择文本样式
+        ("\u{1f600}", 2),
+        ("\u{1f44d}\u{1f3fd}", 2),                         // 肤色修饰符
+        ("\u{270b}\u{1f3ff}", 2),                          // 文本样式字符加肤色修饰符
+        ("\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}", 2), // 家庭ZWJ序列
+        ("\u{1f3f3}\u{fe0f}\u{200d}\u{1f308}", 2),         // 彩虹旗ZWJ序列
+        ("\u{1f1ef}\u{1f1f5}", 2),                         // 旗帜
+        ("\u{1f1e6}", 1),                                  // 单独的区域指示符
+        ("#\u{fe0f}\u{20e3}", 2),                          // 键帽
+    ];
+    for (grapheme, width) in corpus {
+        assert_eq!(model.grapheme_width(grapheme), width, "{grapheme:?}");
+    }
+    let probed = WidthModel::probe(|sample| (sample == EmojiSequence::Zwj.sample()).then_some(6));
+    assert_eq!(probed.grapheme_width("\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}"), 6);
+    assert_eq!(probed.grapheme_width("\u{1f1ef}\u{1f1f5}"), 2);
+}
@@ -6,6 +6,7 @@
     clippy::as_conversions, 
     clippy::integer_division
 )]
+#![cfg_attr(test, allow(clippy::print_stdout))]
 mod editor;
 use editor::Editor;
 